@@ -0,0 +1,11 @@
+-- Add migration script here
+CREATE TABLE IF NOT EXISTS user_lead_time (
+    id INTEGER PRIMARY KEY NOT NULL,
+    user_id INTEGER NOT NULL,
+    minutes INTEGER NOT NULL,
+    UNIQUE(user_id, minutes),
+    FOREIGN KEY(user_id) REFERENCES user(tg_user_id)
+);
+
+-- Keep previous behaviour for already registered users
+INSERT INTO user_lead_time (user_id, minutes) SELECT tg_user_id, 15 FROM user;
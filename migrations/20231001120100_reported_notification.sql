@@ -0,0 +1,10 @@
+-- Add migration script here
+CREATE TABLE IF NOT EXISTS reported_notification (
+    id INTEGER PRIMARY KEY NOT NULL,
+    video_id VARCHAR(64) NOT NULL,
+    user_id INTEGER NOT NULL,
+    lead_time INTEGER NOT NULL,
+    scheduled_start DATETIME NOT NULL,
+    UNIQUE(video_id, user_id, lead_time),
+    FOREIGN KEY(user_id) REFERENCES user(tg_user_id)
+);
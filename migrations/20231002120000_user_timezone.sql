@@ -0,0 +1,2 @@
+-- Add migration script here
+ALTER TABLE user ADD COLUMN timezone VARCHAR(64) NOT NULL DEFAULT 'Europe/Moscow';
@@ -0,0 +1,17 @@
+-- Add migration script here
+ALTER TABLE user ADD COLUMN started_ping BOOLEAN NOT NULL DEFAULT 0;
+
+-- Lead time of reported notification becomes reminder stage ('before_<minutes>' or 'started')
+CREATE TABLE IF NOT EXISTS reported_notification_staged (
+    id INTEGER PRIMARY KEY NOT NULL,
+    video_id VARCHAR(64) NOT NULL,
+    user_id INTEGER NOT NULL,
+    stage VARCHAR(32) NOT NULL,
+    scheduled_start DATETIME NOT NULL,
+    UNIQUE(video_id, user_id, stage),
+    FOREIGN KEY(user_id) REFERENCES user(tg_user_id)
+);
+INSERT INTO reported_notification_staged (video_id, user_id, stage, scheduled_start)
+    SELECT video_id, user_id, 'before_' || lead_time, scheduled_start FROM reported_notification;
+DROP TABLE reported_notification;
+ALTER TABLE reported_notification_staged RENAME TO reported_notification;
@@ -0,0 +1,11 @@
+-- Add migration script here
+CREATE TABLE IF NOT EXISTS user_video_kind (
+    id INTEGER PRIMARY KEY NOT NULL,
+    user_id INTEGER NOT NULL,
+    kind VARCHAR(32) NOT NULL,
+    UNIQUE(user_id, kind),
+    FOREIGN KEY(user_id) REFERENCES user(tg_user_id)
+);
+
+-- Streams were the only kind before
+INSERT INTO user_video_kind (user_id, kind) SELECT tg_user_id, 'stream' FROM user;
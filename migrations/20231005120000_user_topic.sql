@@ -0,0 +1,10 @@
+-- Add migration script here
+ALTER TABLE user ADD COLUMN topic_mode VARCHAR(16) NOT NULL DEFAULT 'blacklist';
+
+CREATE TABLE IF NOT EXISTS user_topic (
+    id INTEGER PRIMARY KEY NOT NULL,
+    user_id INTEGER NOT NULL,
+    topic VARCHAR(64) NOT NULL,
+    UNIQUE(user_id, topic),
+    FOREIGN KEY(user_id) REFERENCES user(tg_user_id)
+);
@@ -0,0 +1,2 @@
+-- Add migration script here
+ALTER TABLE user ADD COLUMN digest_mode BOOLEAN NOT NULL DEFAULT 0;
@@ -0,0 +1,12 @@
+-- Add migration script here
+ALTER TABLE user ADD COLUMN digest_time VARCHAR(5);
+ALTER TABLE user ADD COLUMN weekly_digest BOOLEAN NOT NULL DEFAULT 0;
+
+CREATE TABLE IF NOT EXISTS sent_digest (
+    id INTEGER PRIMARY KEY NOT NULL,
+    user_id INTEGER NOT NULL,
+    kind VARCHAR(16) NOT NULL,
+    period VARCHAR(16) NOT NULL,
+    UNIQUE(user_id, kind, period),
+    FOREIGN KEY(user_id) REFERENCES user(tg_user_id)
+);
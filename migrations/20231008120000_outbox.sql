@@ -0,0 +1,25 @@
+-- Add migration script here
+-- One row per (user, video, reminder stage). Several rows can be delivered with one message
+-- (collapsed stages, digest mode): message is kept in the parent row, others refer to it
+CREATE TABLE IF NOT EXISTS outbox (
+    id INTEGER PRIMARY KEY NOT NULL,
+    user_id INTEGER NOT NULL,
+    chat_id INTEGER NOT NULL,
+    video_id VARCHAR(64) NOT NULL,
+    stage VARCHAR(32) NOT NULL,
+    scheduled_start DATETIME NOT NULL,
+    parent_id INTEGER,
+    photo_url VARCHAR(256),
+    text TEXT NOT NULL,
+    status VARCHAR(16) NOT NULL DEFAULT 'pending',
+    attempts INTEGER NOT NULL DEFAULT 0,
+    last_error TEXT,
+    next_attempt_at DATETIME NOT NULL,
+    created_at DATETIME NOT NULL,
+    updated_at DATETIME NOT NULL,
+    UNIQUE(user_id, video_id, stage, scheduled_start),
+    FOREIGN KEY(user_id) REFERENCES user(tg_user_id),
+    FOREIGN KEY(parent_id) REFERENCES outbox(id)
+);
+
+CREATE INDEX IF NOT EXISTS outbox_pending ON outbox(status, next_attempt_at);
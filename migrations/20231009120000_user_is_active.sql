@@ -0,0 +1,3 @@
+-- Add migration script here
+-- Users, who blocked the bot or deleted their account, are skipped until they /start again
+ALTER TABLE user ADD COLUMN is_active BOOLEAN NOT NULL DEFAULT 1;
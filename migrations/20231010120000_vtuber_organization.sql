@@ -0,0 +1,4 @@
+-- Add migration script here
+-- Catalog was NijiEN-only, so existing vtubers belong to Nijisanji EN
+ALTER TABLE vtuber ADD COLUMN organization VARCHAR(256) NOT NULL DEFAULT 'Nijisanji';
+ALTER TABLE vtuber ADD COLUMN branch VARCHAR(256) NOT NULL DEFAULT 'EN';
@@ -0,0 +1,5 @@
+-- Add migration script here
+-- Channels, added by holodex sync, are hidden from the catalog until admin confirms them
+ALTER TABLE vtuber ADD COLUMN is_confirmed BOOLEAN NOT NULL DEFAULT 1;
+-- Channel name on holodex, saved to detect changes
+ALTER TABLE vtuber ADD COLUMN holodex_name VARCHAR(256);
@@ -0,0 +1,14 @@
+-- Add migration script here
+-- Lifecycle status of vtuber: 'active', 'graduated' or 'hiatus'
+ALTER TABLE vtuber ADD COLUMN status VARCHAR(16) NOT NULL DEFAULT 'active';
+
+-- Subscribers, that got notice about status of vtuber. Every status is announced once
+CREATE TABLE IF NOT EXISTS vtuber_status_notice (
+    id INTEGER PRIMARY KEY NOT NULL,
+    user_id INTEGER NOT NULL,
+    vtuber_id INTEGER NOT NULL,
+    status VARCHAR(16) NOT NULL,
+    UNIQUE(user_id, vtuber_id, status),
+    FOREIGN KEY(user_id) REFERENCES user(tg_user_id),
+    FOREIGN KEY(vtuber_id) REFERENCES vtuber(id)
+);
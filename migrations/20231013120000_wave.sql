@@ -0,0 +1,14 @@
+-- Add migration script here
+-- Waves (groups inside branch) with their display order in the catalog. Filled from
+-- data.json on startup
+CREATE TABLE IF NOT EXISTS wave (
+    id INTEGER PRIMARY KEY NOT NULL,
+    organization VARCHAR(256) NOT NULL,
+    branch VARCHAR(256) NOT NULL,
+    name VARCHAR(256) NOT NULL,
+    display_order INTEGER NOT NULL,
+    debut_date DATE,
+    description TEXT,
+    UNIQUE(organization, branch, name)
+);
+
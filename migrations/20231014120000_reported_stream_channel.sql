@@ -0,0 +1,9 @@
+-- Add migration script here
+-- Name of the channel, where the stream takes place. Stream, where only guests are tracked,
+-- is hosted by a channel outside of the catalog
+ALTER TABLE reported_stream ADD COLUMN channel_name VARCHAR(256) NOT NULL DEFAULT '';
+
+UPDATE reported_stream SET channel_name = (
+    SELECT vtuber.first_name || ' ' || vtuber.last_name FROM vtuber
+    WHERE vtuber.id = reported_stream.vtuber_id
+);
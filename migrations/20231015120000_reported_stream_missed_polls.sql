@@ -0,0 +1,3 @@
+-- Add migration script here
+-- Polls in a row, that didn't return the stream. Stream is considered deleted after several
+ALTER TABLE reported_stream ADD COLUMN missed_polls INTEGER NOT NULL DEFAULT 0;
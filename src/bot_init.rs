@@ -30,6 +30,8 @@ pub async fn init_app() -> anyhow::Result<MelatoninBotState> {
     let config = init_config();
 
     init_db(config.sql_connection_string.clone(), config.max_connections).await?;
+    init_holodex(&config.holodex_api_key)?;
+    init_send_queue(&config.telegram_bot_token)?;
     let state = init_bot_state(config);
     state.init_startup_data().await?;
     Ok(state)
@@ -0,0 +1,168 @@
+use log::{error, info, warn};
+use sqlx::{Pool, Sqlite};
+
+use crate::queries;
+use crate::vtuber::{Vtuber, VtuberOrganization, Wave};
+
+// Reconcile catalog from data.json with db, keyed by youtube channel id: new vtubers are
+// inserted, changed ones are updated and every change is logged. Ambiguous entries are
+// skipped, so a typo in data.json never moves subscriptions to another vtuber
+pub async fn reconcile(
+    pool: Pool<Sqlite>,
+    organizations: Vec<VtuberOrganization>,
+) -> anyhow::Result<()> {
+    let mut members: Vec<Vtuber> = Vec::new();
+    for organization in organizations {
+        for branch in organization.branches {
+            // Waves are shown in order of data.json
+            for (index, wave) in branch.waves.into_iter().enumerate() {
+                queries::upsert_wave(
+                    pool.clone(),
+                    &Wave {
+                        organization: organization.name.clone(),
+                        branch: branch.name.clone(),
+                        name: wave.name.clone(),
+                        display_order: index as i64 + 1,
+                        debut_date: wave.debut_date,
+                        description: wave.description.clone(),
+                        ..Default::default()
+                    },
+                )
+                .await?;
+                for mut member in wave.members {
+                    member.organization = organization.name.clone();
+                    member.branch = branch.name.clone();
+                    member.wave_name = wave.name.clone();
+                    members.push(member);
+                }
+            }
+        }
+    }
+    let (mut inserted, mut updated, mut skipped) = (0, 0, 0);
+    for member in &members {
+        // Same channel twice in data.json - no way to know, which entry is right
+        if members
+            .iter()
+            .filter(|x| x.youtube_channel_id == member.youtube_channel_id)
+            .count()
+            > 1
+        {
+            error!(
+                "Catalog: channel {} is listed several times in data.json, skipped",
+                member.youtube_channel_id
+            );
+            skipped += 1;
+            continue;
+        }
+        match queries::get_vtuber_by_channel_id(pool.clone(), &member.youtube_channel_id).await? {
+            Some(vtuber) => {
+                let changes = diff(&vtuber, member);
+                if changes.is_empty() && vtuber.is_confirmed {
+                    continue;
+                }
+                info!(
+                    "Catalog: update {} {} ({}): {}",
+                    vtuber.first_name,
+                    vtuber.last_name,
+                    vtuber.youtube_channel_id,
+                    changes.join(", ")
+                );
+                let mut updated_vtuber = member.clone();
+                updated_vtuber.id = vtuber.id;
+                queries::update_vtuber(pool.clone(), &updated_vtuber).await?;
+                if !member.status.is_empty() && member.status != vtuber.status {
+                    queries::update_vtuber_status(pool.clone(), vtuber.id, member.get_status())
+                        .await?;
+                }
+                updated += 1;
+            }
+            None => {
+                // Vtuber with the same name on another channel, that data.json doesn't list
+                // anymore, looks like changed channel id. Moving subscriptions to the new
+                // channel is up to admin
+                let namesakes = queries::get_vtubers_by_name(
+                    pool.clone(),
+                    &member.first_name,
+                    &member.last_name,
+                )
+                .await?
+                .into_iter()
+                .filter(|x| {
+                    !members
+                        .iter()
+                        .any(|y| y.youtube_channel_id == x.youtube_channel_id)
+                })
+                .collect::<Vec<_>>();
+                if !namesakes.is_empty() {
+                    error!(
+                        "Catalog: {} {} ({}) has the same name as {}, but another channel id. \
+                        Fix channel id in db or data.json, skipped",
+                        member.first_name,
+                        member.last_name,
+                        member.youtube_channel_id,
+                        namesakes
+                            .iter()
+                            .map(|x| format!("id {} ({})", x.id, x.youtube_channel_id))
+                            .collect::<Vec<_>>()
+                            .join(", ")
+                    );
+                    skipped += 1;
+                    continue;
+                }
+                info!(
+                    "Catalog: insert {} {} ({}) into {} / {} / {}",
+                    member.first_name,
+                    member.last_name,
+                    member.youtube_channel_id,
+                    member.organization,
+                    member.branch,
+                    member.wave_name
+                );
+                queries::insert_vtuber(pool.clone(), member).await?;
+                inserted += 1;
+            }
+        }
+    }
+    // Vtubers are never deleted automatically: it would drop subscriptions
+    for vtuber in queries::get_vtubers(pool.clone()).await? {
+        if vtuber.is_confirmed
+            && !members
+                .iter()
+                .any(|x| x.youtube_channel_id == vtuber.youtube_channel_id)
+        {
+            warn!(
+                "Catalog: {} {} ({}) is in db, but not in data.json",
+                vtuber.first_name, vtuber.last_name, vtuber.youtube_channel_id
+            );
+        }
+    }
+    info!(
+        "Catalog reconciled: {} inserted, {} updated, {} skipped",
+        inserted, updated, skipped
+    );
+    Ok(())
+}
+
+// Describe changed fields of vtuber. Empty status in data.json keeps the one in db
+fn diff(old: &Vtuber, new: &Vtuber) -> Vec<String> {
+    let mut changes = Vec::new();
+    let mut compare = |field: &str, old: &str, new: &str| {
+        if old != new {
+            changes.push(format!("{}: '{}' -> '{}'", field, old, new));
+        }
+    };
+    compare("first_name", &old.first_name, &new.first_name);
+    compare("last_name", &old.last_name, &new.last_name);
+    compare("emoji", &old.emoji, &new.emoji);
+    compare("youtube_handle", &old.youtube_handle, &new.youtube_handle);
+    compare("organization", &old.organization, &new.organization);
+    compare("branch", &old.branch, &new.branch);
+    compare("wave", &old.wave_name, &new.wave_name);
+    if !new.status.is_empty() {
+        compare("status", &old.status, &new.status);
+    }
+    if !old.is_confirmed {
+        changes.push(String::from("confirmed"));
+    }
+    changes
+}
@@ -0,0 +1,373 @@
+use holodex::model::{builders::ChannelFilterBuilder, Channel, Organisation};
+use log::{info, warn};
+use mobot::api::{self, SendMessageRequest};
+use sqlx::{Pool, Sqlite};
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use crate::holodex_request::{self, PAGE_SIZE};
+use crate::main_client::MainClient;
+use crate::queries;
+use crate::send_queue::OutboundMessage;
+use crate::vtuber::Vtuber;
+use crate::vtuber_status::VtuberStatus;
+
+// Branch and wave of synced channels, until admin places them in the catalog
+pub const UNSORTED_GROUP: &str = "Новые";
+
+// Safety limit of channel pages for one organization
+const MAX_PAGES: u32 = 20;
+
+// Max changes in one summary message for admins
+const SUMMARY_PAGE_SIZE: usize = 15;
+
+// Max length of summary message, telegram allows 4096 characters
+const SUMMARY_MAX_LENGTH: usize = 4000;
+
+// Change of the catalog, found by sync
+enum CatalogChange {
+    // Channel wasn't in the catalog, it's added unconfirmed
+    New(Vtuber),
+    // Organization, holodex name or status of the channel changed. Organization is kept,
+    // until admin confirms the move
+    Changed(Vtuber, Vec<String>),
+}
+
+// Pull channels of every organization of the catalog from holodex, add new channels and
+// update changed ones. Admins get one summary of changes per organization
+pub async fn sync_catalog(main_client: &MainClient) -> anyhow::Result<()> {
+    let pool = main_client.get_pool();
+    for organization in queries::get_organizations(pool.clone()).await? {
+        // Holodex knows organizations by their names
+        let holodex_organization: Organisation =
+            match serde_json::from_value(serde_json::Value::String(organization.clone())) {
+                Ok(x) => x,
+                Err(_) => {
+                    warn!(
+                        "Organization {} is unknown to holodex, skipped",
+                        organization
+                    );
+                    continue;
+                }
+            };
+        let channels =
+            fetch_channels(main_client.holodex_api.clone(), holodex_organization).await?;
+        info!("Synced {}: {} channels", organization, channels.len());
+        let mut known = Vec::new();
+        for channel in &channels {
+            let vtuber =
+                queries::get_vtuber_by_channel_id(pool.clone(), &channel.id.to_string()).await?;
+            known.push((channel, vtuber));
+        }
+        let branches = group_branches(known.iter().filter_map(|(channel, vtuber)| {
+            vtuber
+                .as_ref()
+                .map(|vtuber| (channel.group.as_deref(), vtuber))
+        }));
+        let mut changes = Vec::new();
+        for (channel, vtuber) in known {
+            let change = match vtuber {
+                Some(vtuber) => {
+                    update_channel(pool.clone(), &organization, channel, vtuber).await?
+                }
+                // Only groups, that the catalog already has, are synced
+                None => match channel.group.as_ref().and_then(|group| branches.get(group)) {
+                    Some(branch) => {
+                        add_channel(pool.clone(), &organization, branch, channel).await?
+                    }
+                    None => None,
+                },
+            };
+            changes.extend(change);
+        }
+        notify_admins(main_client, &organization, changes).await;
+    }
+    Ok(())
+}
+
+// Branches of the catalog by holodex groups of their confirmed channels. Channels from other
+// groups of the organization (e.g. branches, that the catalog doesn't have) aren't added
+fn group_branches<'a>(
+    known: impl Iterator<Item = (Option<&'a str>, &'a Vtuber)>,
+) -> HashMap<String, String> {
+    known
+        .filter(|(_, vtuber)| vtuber.is_confirmed)
+        .filter_map(|(group, vtuber)| Some((String::from(group?), vtuber.branch.clone())))
+        .collect()
+}
+
+// Request all channels of organization page by page
+async fn fetch_channels(
+    holodex_api: Arc<holodex::Client>,
+    organization: Organisation,
+) -> anyhow::Result<Vec<Channel>> {
+    let mut channels = Vec::new();
+    for page in 0..MAX_PAGES {
+        let filter = ChannelFilterBuilder::new()
+            .organisation(organization.clone())
+            .limit(PAGE_SIZE)
+            .offset(page * PAGE_SIZE)
+            .build();
+        let page_channels =
+            holodex_request::request(holodex_api.clone(), move |api| Ok(api.channels(&filter)?))
+                .await?;
+        let is_last = (page_channels.len() as u32) < PAGE_SIZE;
+        channels.extend(page_channels);
+        if is_last {
+            break;
+        }
+    }
+    Ok(channels)
+}
+
+// Get channel name, shown on holodex
+fn holodex_name(channel: &Channel) -> String {
+    channel.english_name.clone().unwrap_or(channel.name.clone())
+}
+
+// Add new channel into the branch, unconfirmed
+async fn add_channel(
+    pool: Pool<Sqlite>,
+    organization: &str,
+    branch: &str,
+    channel: &Channel,
+) -> anyhow::Result<Option<CatalogChange>> {
+    // Retired channels aren't added to the catalog
+    if channel.inactive {
+        return Ok(None);
+    }
+    let holodex_name = holodex_name(channel);
+    let (first_name, last_name) = holodex_name
+        .split_once(' ')
+        .unwrap_or((holodex_name.as_str(), ""));
+    let mut vtuber = Vtuber {
+        first_name: String::from(first_name),
+        last_name: String::from(last_name),
+        wave_name: String::from(UNSORTED_GROUP),
+        youtube_channel_id: channel.id.to_string(),
+        organization: String::from(organization),
+        branch: String::from(branch),
+        holodex_name: Some(holodex_name.clone()),
+        ..Default::default()
+    };
+    vtuber.id = queries::insert_synced_vtuber(pool, &vtuber).await?;
+    info!(
+        "New channel {} ({})",
+        holodex_name, vtuber.youtube_channel_id
+    );
+    Ok(Some(CatalogChange::New(vtuber)))
+}
+
+// Update holodex name and status of the channel from the catalog. Organization isn't changed
+// here - admin confirms the move
+async fn update_channel(
+    pool: Pool<Sqlite>,
+    organization: &str,
+    channel: &Channel,
+    vtuber: Vtuber,
+) -> anyhow::Result<Option<CatalogChange>> {
+    let holodex_name = holodex_name(channel);
+    let mut changes = Vec::new();
+    if vtuber.organization != organization {
+        changes.push(format!(
+            "организация: {} → {} (ждет подтверждения)",
+            vtuber.organization, organization
+        ));
+    }
+    // First sync of the channel only remembers its name
+    if let Some(old_name) = &vtuber.holodex_name {
+        if *old_name != holodex_name {
+            changes.push(format!("имя на holodex: {} → {}", old_name, holodex_name));
+        }
+    }
+    if vtuber.holodex_name.as_deref() != Some(holodex_name.as_str()) {
+        queries::update_vtuber_holodex_name(pool.clone(), vtuber.id, &holodex_name).await?;
+    }
+    // Holodex marks graduated channels as inactive
+    if channel.inactive && vtuber.get_status() != VtuberStatus::Graduated {
+        changes.push(format!(
+            "статус: {} → {}",
+            vtuber.status,
+            VtuberStatus::Graduated
+        ));
+        queries::update_vtuber_status(pool, vtuber.id, VtuberStatus::Graduated).await?;
+    }
+    match changes.is_empty() {
+        true => Ok(None),
+        false => Ok(Some(CatalogChange::Changed(vtuber, changes))),
+    }
+}
+
+// Command for admin to place vtuber in the catalog
+fn edit_command(vtuber: &Vtuber) -> String {
+    format!(
+        "/vtuber {} {} {} | {} | {} | {}",
+        vtuber.id,
+        vtuber.first_name,
+        vtuber.last_name,
+        vtuber.branch,
+        vtuber.wave_name,
+        vtuber.emoji
+    )
+}
+
+// Entry of the change in summary: confirm new channel as is or set its place in the catalog
+fn change_entry(change: &CatalogChange) -> String {
+    match change {
+        CatalogChange::New(vtuber) => format!(
+            "🆕 Новый канал: {}\n\
+            https://www.youtube.com/channel/{}\n\
+            {}",
+            vtuber.holodex_name.clone().unwrap_or_default(),
+            vtuber.youtube_channel_id,
+            edit_command(vtuber)
+        ),
+        CatalogChange::Changed(vtuber, changes) => format!(
+            "✏️ Изменен канал {} {}\n\
+            {}\n\
+            https://www.youtube.com/channel/{}\n\
+            {}",
+            vtuber.first_name,
+            vtuber.last_name,
+            changes.join("\n"),
+            vtuber.youtube_channel_id,
+            edit_command(vtuber)
+        ),
+    }
+}
+
+// Split changes into pages, limited by amount of entries and by message length
+fn summary_pages(changes: &[CatalogChange]) -> Vec<&[CatalogChange]> {
+    let mut pages = Vec::new();
+    let mut start = 0;
+    let mut length = 0;
+    for (i, change) in changes.iter().enumerate() {
+        let entry_length = change_entry(change).chars().count() + 2;
+        if i > start
+            && (i - start == SUMMARY_PAGE_SIZE || length + entry_length > SUMMARY_MAX_LENGTH)
+        {
+            pages.push(&changes[start..i]);
+            start = i;
+            length = 0;
+        }
+        length += entry_length;
+    }
+    if start < changes.len() {
+        pages.push(&changes[start..]);
+    }
+    pages
+}
+
+// Send admins summary of the organization sync. New channels are hidden from the catalog,
+// until admin confirms them with the button or places them with the command. Moves to
+// another organization are applied by the button only
+async fn notify_admins(main_client: &MainClient, organization: &str, changes: Vec<CatalogChange>) {
+    let pages = summary_pages(&changes);
+    for (i, page) in pages.iter().enumerate() {
+        let text = format!(
+            "Синхронизация {} ({}/{}). Новые каналы скрыты из каталога до подтверждения. \
+            Имя, отделение, волна и эмодзи задаются командой /vtuber. \
+            Перенос в другую организацию нужно повторить в data.json\n\n{}",
+            organization,
+            i + 1,
+            pages.len(),
+            page.iter()
+                .map(change_entry)
+                .collect::<Vec<_>>()
+                .join("\n\n")
+        );
+        let buttons = page
+            .iter()
+            .filter_map(|change| match change {
+                CatalogChange::New(vtuber) => Some(vec![api::InlineKeyboardButton::from(format!(
+                    "✅ {}",
+                    vtuber.holodex_name.clone().unwrap_or_default()
+                ))
+                .with_callback_data(format!("confirm_{}", vtuber.id))]),
+                // Move to the organization of the sync
+                CatalogChange::Changed(vtuber, _) if vtuber.organization != organization => Some(
+                    vec![api::InlineKeyboardButton::from(format!(
+                        "🔀 {} {} → {}",
+                        vtuber.first_name, vtuber.last_name, organization
+                    ))
+                    .with_callback_data(format!("move_{}_{}", vtuber.id, organization))],
+                ),
+                CatalogChange::Changed(..) => None,
+            })
+            .collect::<Vec<_>>();
+        for admin_id in &main_client.admin_ids {
+            let mut req = SendMessageRequest::new(*admin_id, text.clone());
+            if !buttons.is_empty() {
+                req = req
+                    .with_reply_markup(api::ReplyMarkup::inline_keyboard_markup(buttons.clone()));
+            }
+            if let Err(e) = main_client
+                .send_queue
+                .send(OutboundMessage::Text(req))
+                .await
+            {
+                warn!("Admin-notify failed for UserID<{}>: {}", admin_id, e);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn new_channel(name: &str) -> CatalogChange {
+        CatalogChange::New(Vtuber {
+            holodex_name: Some(String::from(name)),
+            ..Default::default()
+        })
+    }
+
+    #[test]
+    fn summary_is_paged_by_entries() {
+        let changes = (0..SUMMARY_PAGE_SIZE + 2)
+            .map(|i| new_channel(&format!("Channel {}", i)))
+            .collect::<Vec<_>>();
+        let pages = summary_pages(&changes);
+        assert_eq!(pages.len(), 2);
+        assert_eq!(pages[0].len(), SUMMARY_PAGE_SIZE);
+        assert_eq!(pages[1].len(), 2);
+    }
+
+    #[test]
+    fn summary_is_paged_by_length() {
+        let long_name = "x".repeat(SUMMARY_MAX_LENGTH / 3);
+        let changes = (0..4).map(|_| new_channel(&long_name)).collect::<Vec<_>>();
+        let pages = summary_pages(&changes);
+        assert_eq!(pages.len(), 2);
+        assert!(pages.iter().all(|page| {
+            page.iter()
+                .map(|change| change_entry(change).chars().count() + 2)
+                .sum::<usize>()
+                <= SUMMARY_MAX_LENGTH
+        }));
+    }
+
+    #[test]
+    fn only_groups_of_confirmed_channels_are_synced() {
+        let confirmed = Vtuber {
+            branch: String::from("EN"),
+            is_confirmed: true,
+            ..Default::default()
+        };
+        let unsorted = Vtuber {
+            branch: String::from(UNSORTED_GROUP),
+            ..Default::default()
+        };
+        let branches = group_branches(
+            vec![
+                (Some("NIJISANJI EN"), &confirmed),
+                (Some("NIJISANJI ID"), &unsorted),
+                (None, &confirmed),
+            ]
+            .into_iter(),
+        );
+        assert_eq!(branches.len(), 1);
+        assert_eq!(branches["NIJISANJI EN"], "EN");
+    }
+}
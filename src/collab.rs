@@ -0,0 +1,156 @@
+use crate::vtuber::Vtuber;
+
+// Characters, allowed in youtube handles and channel ids
+fn is_id_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || c == '_' || c == '-' || c == '.'
+}
+
+// Take id from the beginning of the text. Trailing dots are end of the sentence, not the id
+fn take_id(text: &str) -> String {
+    text.chars()
+        .take_while(|c| is_id_char(*c))
+        .collect::<String>()
+        .trim_end_matches('.')
+        .to_string()
+}
+
+// Extract '@handle' mentions (including 'youtube.com/@handle' links) from the description.
+// Handles are returned in lowercase with '@'
+pub fn extract_handles(description: &str) -> Vec<String> {
+    let mut handles = Vec::new();
+    for (index, _) in description.match_indices('@') {
+        // Skip e-mails and similar: handle starts after whitespace, punctuation or '/'
+        let is_mention = description[..index]
+            .chars()
+            .last()
+            .map_or(true, |c| !c.is_alphanumeric());
+        let handle = take_id(&description[index + 1..]);
+        if is_mention && !handle.is_empty() {
+            let handle = format!("@{}", handle.to_lowercase());
+            if !handles.contains(&handle) {
+                handles.push(handle);
+            }
+        }
+    }
+    handles
+}
+
+// Extract channel ids from 'youtube.com/channel/<id>' links in the description
+pub fn extract_channel_ids(description: &str) -> Vec<String> {
+    let mut ids = Vec::new();
+    for (index, pattern) in description.match_indices("youtube.com/channel/") {
+        let id = take_id(&description[index + pattern.len()..]);
+        if !id.is_empty() && !ids.contains(&id) {
+            ids.push(id);
+        }
+    }
+    ids
+}
+
+// Find vtubers, that are mentioned in the description by handle or channel link
+pub fn find_partners<'a>(description: &str, vtubers: &'a [Vtuber]) -> Vec<&'a Vtuber> {
+    let handles = extract_handles(description);
+    let channel_ids = extract_channel_ids(description);
+    vtubers
+        .iter()
+        .filter(|vtuber| {
+            let handle = format!(
+                "@{}",
+                vtuber.youtube_handle.trim_start_matches('@').to_lowercase()
+            );
+            handles.contains(&handle) || channel_ids.contains(&vtuber.youtube_channel_id)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn handles_are_lowercased_and_deduplicated() {
+        assert_eq!(
+            extract_handles("Collab with @PomuRainpuff and @pomurainpuff!"),
+            vec!["@pomurainpuff"]
+        );
+    }
+
+    #[test]
+    fn handle_links_are_found() {
+        assert_eq!(
+            extract_handles("Guest: https://www.youtube.com/@EliraPendora/streams"),
+            vec!["@elirapendora"]
+        );
+    }
+
+    #[test]
+    fn emails_are_not_handles() {
+        assert!(extract_handles("Business inquiries: contact@nijisanji.com").is_empty());
+        assert_eq!(
+            extract_handles("Mail: fan@letters.jp, guest (@Finana_Ryugu)"),
+            vec!["@finana_ryugu"]
+        );
+    }
+
+    #[test]
+    fn trailing_dots_end_the_sentence() {
+        assert_eq!(
+            extract_handles("Thanks for coming, @Petra-Gurin... See you!"),
+            vec!["@petra-gurin"]
+        );
+        assert_eq!(
+            extract_channel_ids("Her channel: youtube.com/channel/UCP4nMSTdwU1KqYWu3UH5DHQ."),
+            vec!["UCP4nMSTdwU1KqYWu3UH5DHQ"]
+        );
+    }
+
+    #[test]
+    fn lonely_at_sign_is_skipped() {
+        assert!(extract_handles("Starts @ 9 PM").is_empty());
+        assert!(extract_handles("@").is_empty());
+    }
+
+    #[test]
+    fn channel_ids_are_taken_from_links() {
+        assert_eq!(
+            extract_channel_ids(
+                "https://www.youtube.com/channel/UCIeSUTOTkF9Hs7q3SGcO-Ow?sub_confirmation=1\n\
+                https://youtube.com/channel/UCIeSUTOTkF9Hs7q3SGcO-Ow"
+            ),
+            vec!["UCIeSUTOTkF9Hs7q3SGcO-Ow"]
+        );
+        assert!(extract_channel_ids("youtube.com/channel/").is_empty());
+    }
+
+    #[test]
+    fn partners_are_matched_by_handle_or_channel() {
+        let vtubers = vec![
+            Vtuber {
+                id: 1,
+                youtube_handle: String::from("@PomuRainpuff"),
+                youtube_channel_id: String::from("UCP4nMSTdwU1KqYWu3UH5DHQ"),
+                ..Default::default()
+            },
+            Vtuber {
+                id: 2,
+                youtube_handle: String::from("EliraPendora"),
+                youtube_channel_id: String::from("UCIeSUTOTkF9Hs7q3SGcO-Ow"),
+                ..Default::default()
+            },
+            Vtuber {
+                id: 3,
+                youtube_handle: String::from("@FinanaRyugu"),
+                youtube_channel_id: String::from("UCu-J8uIXuLZh16gG-cT1naw"),
+                ..Default::default()
+            },
+        ];
+        let partners = find_partners(
+            "w/ @elirapendora and youtube.com/channel/UCP4nMSTdwU1KqYWu3UH5DHQ",
+            &vtubers,
+        );
+        assert_eq!(
+            partners.iter().map(|x| x.id).collect::<Vec<_>>(),
+            vec![1, 2]
+        );
+    }
+}
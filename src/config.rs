@@ -9,10 +9,12 @@ use sqlx::Sqlite;
 
 use std::collections::HashMap;
 use std::fs;
+use std::sync::Arc;
 use tokio::sync::OnceCell;
 
-use crate::queries;
-use crate::queries::insert_vtuber;
+use crate::catalog;
+use crate::send_queue::SendQueue;
+use crate::video_kind::{VideoKind, VIDEO_KIND_OPTIONS};
 use crate::vtuber;
 
 // Config to keep secrets and stuff
@@ -24,14 +26,52 @@ pub struct Config {
     pub telegram_bot_token: String,
     // Connection string for sqlite db
     pub sql_connection_string: String,
-    // Data about NijiEN waves
+    // Catalog of vtubers: organizations, branches and waves
     pub startup_data_path: String,
     // Max db connections
     pub max_connections: u32,
     // Time between fetching of videos
     pub timer_duration_sec: u64,
     // IP of monitoring service to send notification for admins
-    pub monitoring_ip: String
+    pub monitoring_ip: String,
+    // Minimal shift of stream start (in minutes) to notify users about reschedule
+    #[serde(default = "default_reschedule_threshold_min")]
+    pub reschedule_threshold_min: i64,
+    // Kinds of videos to fetch: stream, premiere, placeholder
+    #[serde(default = "default_video_types")]
+    pub video_types: Vec<String>,
+    // How far ahead /schedule looks for streams
+    #[serde(default = "default_schedule_horizon_hours")]
+    pub schedule_horizon_hours: u32,
+    // How many users are notified at the same time
+    #[serde(default = "default_send_parallelism")]
+    pub send_parallelism: usize,
+    // Telegram ids of admins, who confirm channels from holodex sync
+    #[serde(default)]
+    pub admin_ids: Vec<i64>,
+    // Time between catalog syncs with holodex, 0 disables sync
+    #[serde(default = "default_catalog_sync_hours")]
+    pub catalog_sync_hours: u64,
+}
+
+fn default_reschedule_threshold_min() -> i64 {
+    5
+}
+
+fn default_video_types() -> Vec<String> {
+    vec![VideoKind::Stream.to_string()]
+}
+
+fn default_schedule_horizon_hours() -> u32 {
+    48
+}
+
+fn default_send_parallelism() -> usize {
+    8
+}
+
+fn default_catalog_sync_hours() -> u64 {
+    24
 }
 
 // Bot state, containts config data and pool of connections
@@ -41,6 +81,10 @@ pub struct MelatoninBotState {
     config: Config,
     // Sql connections pool
     sql_pool: MyPool,
+    // Holodex client, shared by handlers and main client
+    holodex_api: MyHolodex,
+    // Telegram send queue, shared by handlers and main client
+    send_queue: MySendQueue,
 }
 
 impl MelatoninBotState {
@@ -48,15 +92,21 @@ impl MelatoninBotState {
         MelatoninBotState {
             config: config,
             sql_pool: MyPool::default(),
+            holodex_api: MyHolodex::default(),
+            send_queue: MySendQueue::default(),
         }
     }
     // Get telegram bot token
     pub fn get_telegram_bot_token(&self) -> String {
         self.config.telegram_bot_token.clone()
     }
-    // Get holodex api key
-    pub fn get_holodex_api_key(&self) -> String {
-        self.config.holodex_api_key.clone()
+    // Get holodex client
+    pub fn get_holodex_api(&self) -> Arc<holodex::Client> {
+        self.holodex_api.0.clone()
+    }
+    // Get telegram send queue
+    pub fn get_send_queue(&self) -> SendQueue {
+        self.send_queue.0.clone()
     }
     // Get timer duration for fetching videos
     pub fn get_timer_duration_sec(&self) -> u64 {
@@ -66,29 +116,66 @@ impl MelatoninBotState {
     pub fn get_pool(&self) -> Pool<Sqlite> {
         self.sql_pool.0.clone()
     }
+    // Get minimal shift of stream start to notify about reschedule
+    pub fn get_reschedule_threshold_min(&self) -> i64 {
+        self.config.reschedule_threshold_min
+    }
+    // Get kinds of videos to fetch in order of settings menu. Unknown kinds are skipped
+    pub fn get_video_kinds(&self) -> Vec<VideoKind> {
+        let configured = self
+            .config
+            .video_types
+            .iter()
+            .filter_map(|x| match x.parse() {
+                Ok(kind) => Some(kind),
+                Err(e) => {
+                    error!("{}", e);
+                    None
+                }
+            })
+            .collect::<Vec<VideoKind>>();
+        VIDEO_KIND_OPTIONS
+            .into_iter()
+            .filter(|x| configured.contains(x))
+            .collect()
+    }
+    // Get horizon of /schedule command
+    pub fn get_schedule_horizon_hours(&self) -> u32 {
+        self.config.schedule_horizon_hours
+    }
+    // Get limit of users, notified at the same time
+    pub fn get_send_parallelism(&self) -> usize {
+        self.config.send_parallelism.max(1)
+    }
+    // Get telegram ids of admins
+    pub fn get_admin_ids(&self) -> Vec<i64> {
+        self.config.admin_ids.clone()
+    }
+    // Get time between catalog syncs
+    pub fn get_catalog_sync_hours(&self) -> u64 {
+        self.config.catalog_sync_hours
+    }
     // Get monitoring IP
     pub fn get_monitoring_ip(&self) -> String {
         self.config.monitoring_ip.clone()
     }
-    // Read data about NijiEN waves and save in database, if there are none
+    // Read catalog of vtubers and reconcile it with database
     pub async fn init_startup_data(&self) -> Result<(), anyhow::Error> {
         let str_data = fs::read_to_string(self.config.startup_data_path.clone()).unwrap();
         let data: HashMap<String, serde_json::Value> = serde_json::from_str(&str_data).unwrap();
-        // Read 'waves' field - it contains array of objects, representing waves
-        let data: Vec<vtuber::VtuberWave> = serde_json::from_value(data["waves"].clone()).unwrap();
-        for wave in data {
-            for mut member in wave.members {
-                member.wave_name = wave.name.clone();
-                match queries::is_vtuber_exist(self.get_pool(), &member).await {
-                    Ok(is_exist) => match is_exist {
-                        true => (),
-                        false => insert_vtuber(self.get_pool(), &member).await?,
-                    },
-                    Err(e) => panic!("{}", e),
-                }
-            }
-        }
-        Ok(())
+        // Read 'organizations' field - it contains array of objects, representing organizations
+        // with their branches and waves. Old format with only 'waves' field is NijiEN catalog
+        let organizations: Vec<vtuber::VtuberOrganization> = match data.get("organizations") {
+            Some(organizations) => serde_json::from_value(organizations.clone()).unwrap(),
+            None => vec![vtuber::VtuberOrganization {
+                name: String::from("Nijisanji"),
+                branches: vec![vtuber::VtuberBranch {
+                    name: String::from("EN"),
+                    waves: serde_json::from_value(data["waves"].clone()).unwrap(),
+                }],
+            }],
+        };
+        catalog::reconcile(self.get_pool(), organizations).await
     }
 }
 
@@ -139,3 +226,61 @@ impl Default for MyPool {
         MyPool(POOL.get().unwrap().clone())
     }
 }
+
+// Singleton of holodex client, kept out of bot state for the same reason as the pool
+static HOLODEX: OnceCell<Arc<holodex::Client>> = OnceCell::const_new();
+
+// Create holodex client with given api key
+pub fn init_holodex(api_key: &str) -> Result<(), anyhow::Error> {
+    HOLODEX
+        .set(Arc::new(holodex::Client::new(api_key)?))
+        .map_err(|_| anyhow::anyhow!("Holodex client is already set"))?;
+    debug!("Global holodex client was set");
+    Ok(())
+}
+
+// Wrapper for holodex client to provide Default trait implementation
+#[derive(Clone)]
+pub struct MyHolodex(pub Arc<holodex::Client>);
+
+impl Default for MyHolodex {
+    fn default() -> Self {
+        MyHolodex(HOLODEX.get().unwrap().clone())
+    }
+}
+
+impl std::fmt::Debug for MyHolodex {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "MyHolodex")
+    }
+}
+
+// Singleton of telegram send queue, kept out of bot state for the same reason as the pool.
+// Rate limits are counted for all messages of the bot in one place
+static SEND_QUEUE: OnceCell<SendQueue> = OnceCell::const_new();
+
+// Create send queue with its own telegram api client
+pub fn init_send_queue(telegram_bot_token: &str) -> Result<(), anyhow::Error> {
+    let tg_api = mobot::API::new(mobot::Client::new(String::from(telegram_bot_token)));
+    SEND_QUEUE
+        .set(SendQueue::new(Arc::new(tg_api)))
+        .map_err(|_| anyhow::anyhow!("Send queue is already set"))?;
+    debug!("Global send queue was set");
+    Ok(())
+}
+
+// Wrapper for send queue to provide Default trait implementation
+#[derive(Clone)]
+pub struct MySendQueue(pub SendQueue);
+
+impl Default for MySendQueue {
+    fn default() -> Self {
+        MySendQueue(SEND_QUEUE.get().unwrap().clone())
+    }
+}
+
+impl std::fmt::Debug for MySendQueue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "MySendQueue")
+    }
+}
@@ -0,0 +1,157 @@
+use chrono::{Datelike, NaiveTime, Timelike, Weekday};
+use log::{error, warn};
+use mobot::api::{ParseMode, SendMessageRequest};
+use std::sync::Arc;
+
+use crate::main_client::MainClient;
+use crate::send_queue::{self, OutboundMessage};
+use crate::user::User;
+use crate::{queries, schedule, timezone};
+
+// Local times of daily digest, offered in settings menu
+pub const DIGEST_TIME_OPTIONS: [&str; 5] = ["07:00", "08:00", "09:00", "10:00", "12:00"];
+
+// Local time of weekly digest for users without daily digest
+pub const DEFAULT_DIGEST_TIME: &str = "09:00";
+
+// Kinds of digests in sent_digest table
+const DAILY_DIGEST: &str = "daily";
+const WEEKLY_DIGEST: &str = "weekly";
+
+// Send daily and weekly digests to users, whose local digest time has come. Users are
+// served concurrently. Every digest is sent once per period (local day or ISO week)
+pub async fn send_digests(main_client: &Arc<MainClient>) -> anyhow::Result<()> {
+    let users = queries::get_digest_users(main_client.get_pool()).await?;
+    main_client
+        .fan_out("sending digests", users, |main_client, user| async move {
+            if let Err(e) = send_user_digests(&main_client, &user).await {
+                error!(
+                    "Error during sending digest to UserID<{}>: {}",
+                    user.tg_user_id, e
+                );
+            }
+            Ok(())
+        })
+        .await
+}
+
+// Send digests of one user, if their time has come
+async fn send_user_digests(main_client: &MainClient, user: &User) -> anyhow::Result<()> {
+    let local_now = timezone::to_user_time(chrono::Utc::now(), &user.timezone);
+    let digest_time = NaiveTime::parse_from_str(
+        user.digest_time.as_deref().unwrap_or(DEFAULT_DIGEST_TIME),
+        "%H:%M",
+    )?;
+    if local_now.time() < digest_time {
+        return Ok(());
+    }
+    if user.digest_time.is_some() {
+        let period = local_now.format("%Y-%m-%d").to_string();
+        if queries::claim_digest(
+            main_client.get_pool(),
+            user.tg_user_id,
+            DAILY_DIGEST,
+            &period,
+        )
+        .await?
+        {
+            // Streams till the end of the local day
+            let horizon_hours = 24 - local_now.hour();
+            let today = local_now.date_naive();
+            let res = send_digest(
+                main_client,
+                user,
+                horizon_hours,
+                "🌅 Стримы на сегодня",
+                |x| timezone::to_user_time(x.available_at, &user.timezone).date_naive() == today,
+            )
+            .await;
+            release_on_error(main_client, user, DAILY_DIGEST, &period, res).await?;
+        }
+    }
+    if user.weekly_digest && local_now.weekday() == Weekday::Sun {
+        let week = local_now.iso_week();
+        let period = format!("{}-W{:02}", week.year(), week.week());
+        if queries::claim_digest(
+            main_client.get_pool(),
+            user.tg_user_id,
+            WEEKLY_DIGEST,
+            &period,
+        )
+        .await?
+        {
+            let res = send_digest(main_client, user, 24 * 7, "🗓 Стримы на неделю", |_| true).await;
+            release_on_error(main_client, user, WEEKLY_DIGEST, &period, res).await?;
+        }
+    }
+    Ok(())
+}
+
+// Period is claimed before sending, so digest isn't sent twice. If holodex or telegram
+// failed temporarily, claim is released and digest is retried on the next check
+async fn release_on_error(
+    main_client: &MainClient,
+    user: &User,
+    kind: &str,
+    period: &str,
+    res: anyhow::Result<()>,
+) -> anyhow::Result<()> {
+    if let Err(e) = res {
+        queries::release_digest(main_client.get_pool(), user.tg_user_id, kind, period).await?;
+        return Err(e);
+    }
+    Ok(())
+}
+
+// Fetch schedule of subscribed vtubers and send it, if there are any streams. Returns error
+// only if it's worth retrying: failure, that repeats on every attempt, keeps the claim
+async fn send_digest(
+    main_client: &MainClient,
+    user: &User,
+    horizon_hours: u32,
+    title: &str,
+    filter: impl Fn(&holodex::model::Video) -> bool,
+) -> anyhow::Result<()> {
+    let vtubers = queries::get_user_vtubers(main_client.get_pool(), user.tg_user_id).await?;
+    if vtubers.is_empty() {
+        return Ok(());
+    }
+    let videos = schedule::fetch_schedule(main_client.holodex_api.clone(), vtubers, horizon_hours)
+        .await?
+        .into_iter()
+        .filter(|x| filter(&x.1))
+        .collect::<Vec<_>>();
+    if videos.is_empty() {
+        return Ok(());
+    }
+    let mut res = Ok(());
+    for text in schedule::render_schedule(&videos, &user.timezone, Some(title)) {
+        res = main_client
+            .send_queue
+            .send(OutboundMessage::Text(
+                SendMessageRequest::new(user.tg_chat_id, text)
+                    .with_parse_mode(ParseMode::MarkdownV2),
+            ))
+            .await;
+        if res.is_err() {
+            break;
+        }
+    }
+    if let Err(e) = res {
+        // Blocked user is deactivated, there is nothing to retry
+        if main_client
+            .handle_send_error(user.tg_user_id, "User-digest", &e)
+            .await
+        {
+            return Ok(());
+        }
+        if send_queue::is_temporary(&e.to_string()) {
+            return Err(e);
+        }
+        warn!(
+            "Digest for UserID<{}> is skipped till the next period",
+            user.tg_user_id
+        );
+    }
+    Ok(())
+}
@@ -1,9 +1,20 @@
+use crate::catalog_sync;
 use crate::config::MelatoninBotState;
+use crate::digest::DIGEST_TIME_OPTIONS;
 use crate::markup::{self, members_markup};
 use crate::queries;
+use crate::reminder::LEAD_TIME_OPTIONS;
+use crate::schedule;
+use crate::send_queue::OutboundMessage;
+use crate::timezone;
+use crate::topic::TOPIC_OPTIONS;
+use crate::video_kind::VideoKind;
+use crate::vtuber_status::VtuberStatus;
 use anyhow::anyhow;
 use log::info;
-use mobot::api::{EditMessageReplyMarkupRequest, EditMessageTextRequest, SendMessageRequest};
+use mobot::api::{
+    EditMessageReplyMarkupRequest, EditMessageTextRequest, ParseMode, SendMessageRequest,
+};
 use mobot::handler::State;
 use mobot::*;
 use std::future::Future;
@@ -26,131 +37,752 @@ pub fn report_action<'a>(
     })(e, s)
 }
 
-// Handle /start command. Send greeting with waves markup
+// Greeting with the catalog of vtubers
+const CATALOG_TEXT: &str = "Данный бот напоминает о стримах выбранных вами втуберов перед началом стрима. Выберите агентство";
+
+// Handle /start command. Send greeting with organizations markup
 pub async fn start_handler(e: Event, s: State<MelatoninBotState>) -> Result<Action, anyhow::Error> {
     let id = get_user_id(&e)?;
-    e
-    .api
-    .send_message(
-        &SendMessageRequest::new(e.update.chat_id()?, "Здравствуйте, данный бот напоминает о стримах выбранных вами втуберов Nijisanji EN за 15-20 минут до начала стрима. Выберите волну")
-    .with_reply_markup(markup::waves_markup(s.get().read().await.get_pool(), id).await)).await?;
+    send(
+        &s,
+        OutboundMessage::Text(SendMessageRequest::new(e.update.chat_id()?, "Здравствуйте, данный бот напоминает о стримах выбранных вами втуберов перед началом стрима. Время напоминаний можно выбрать в /settings. Выберите агентство")
+        .with_reply_markup(markup::organizations_markup(s.get().read().await.get_pool(), id).await)),
+    )
+    .await?;
     let pool = s.get().read().await.get_pool();
     let user = e.update.from_user().unwrap();
-    match queries::insert_user(pool, user, e.update.chat_id().unwrap()).await {
+    let chat_id = e.update.chat_id().unwrap();
+    // Returning user (e.g. unblocked the bot) is reactivated instead of inserted
+    if queries::reactivate_user(pool.clone(), user, chat_id).await? {
+        return Ok(Action::Done);
+    }
+    match queries::insert_user(pool, user, chat_id).await {
         Ok(_) => Ok(Action::Done),
         Err(e) => Err(anyhow!(e)),
     }
 }
 
+// Handle change of bot membership in private chat: user blocked the bot or unblocked it
+pub async fn my_chat_member_handler(
+    e: Event,
+    s: State<MelatoninBotState>,
+) -> Result<Action, anyhow::Error> {
+    let update = e.update.get_my_chat_member()?;
+    let pool = s.get().read().await.get_pool();
+    match update.new_chat_member.status.as_str() {
+        "kicked" => {
+            queries::deactivate_user(pool, update.from.id).await?;
+            info!("UserID<{}> blocked the bot, deactivated", update.from.id);
+        }
+        // Unknown user gets into db with /start
+        "member" => {
+            if queries::reactivate_user(pool, &update.from, update.chat.id).await? {
+                info!("UserID<{}> unblocked the bot, reactivated", update.from.id);
+            }
+        }
+        _ => {}
+    }
+    Ok(Action::Done)
+}
+
 // Handle /about command. Send info about bot
-pub async fn about_handler(e: Event, _: State<MelatoninBotState>) -> Result<Action, anyhow::Error> {
-    e.api
-        .send_message(&SendMessageRequest::new(
+pub async fn about_handler(e: Event, s: State<MelatoninBotState>) -> Result<Action, anyhow::Error> {
+    send(
+        &s,
+        OutboundMessage::Text(SendMessageRequest::new(
             e.update.chat_id()?,
-            "Бот, напоминающий о стримах выбранных втуберов NijiEN за 15-20 минут до начала\n\
+            "Бот, напоминающий о стримах выбранных втуберов перед началом\n\
+            Время напоминаний настраивается командой /settings\n\
             Жалобы/предложения - @DanArmor\n\
             Код бота: https://github.com/DanArmor/melatonin-bot\n\
             Если что-то не работает - попробуйте команду /start\n\
             Если и это не помогло - напишите админу",
-        ))
-        .await?;
+        )),
+    )
+    .await?;
     Ok(Action::Done)
 }
 
-// Handle /waves command. Send generic message with waves markup
+// Handle /waves command. Send generic message with organizations markup
 pub async fn info_handler(e: Event, s: State<MelatoninBotState>) -> Result<Action, anyhow::Error> {
     let id = get_user_id(&e)?;
-    e
-    .api
-    .send_message(
-        &SendMessageRequest::new(e.update.chat_id()?, "Данный бот напоминает о стримах выбранных вами втуберов Nijisanji EN за 15-20 минут до начала стрима. Выберите волну")
-    .with_reply_markup(markup::waves_markup(s.get().read().await.get_pool(), id).await)).await?;
+    send(
+        &s,
+        OutboundMessage::Text(
+            SendMessageRequest::new(e.update.chat_id()?, CATALOG_TEXT).with_reply_markup(
+                markup::organizations_markup(s.get().read().await.get_pool(), id).await,
+            ),
+        ),
+    )
+    .await?;
+    Ok(Action::Done)
+}
+
+// Send message through the rate-limited queue
+async fn send(s: &State<MelatoninBotState>, message: OutboundMessage) -> anyhow::Result<()> {
+    let send_queue = s.get().read().await.get_send_queue();
+    send_queue.send(message).await
+}
+
+// Replace text and markup of the catalog message
+async fn edit_catalog(
+    e: &Event,
+    s: &State<MelatoninBotState>,
+    text: &str,
+    markup: api::ReplyMarkup,
+) -> anyhow::Result<()> {
+    send(
+        s,
+        OutboundMessage::EditText(
+            e.update.chat_id()?,
+            EditMessageTextRequest::new(String::from(text))
+                .with_chat_id(e.update.chat_id()?)
+                .with_message_id(e.update.message_id()?),
+        ),
+    )
+    .await?;
+    send(
+        s,
+        OutboundMessage::EditMarkup(
+            e.update.chat_id()?,
+            EditMessageReplyMarkupRequest::new(markup)
+                .with_chat_id(e.update.chat_id()?)
+                .with_message_id(e.update.message_id()?),
+        ),
+    )
+    .await?;
+    Ok(())
+}
+
+// Handle click on organization button or 'back' button. Show branches of the organization
+// of the vtuber from callback data or return to organizations list
+pub async fn organization_handler(
+    e: Event,
+    s: State<MelatoninBotState>,
+) -> Result<Action, anyhow::Error> {
+    let id = get_user_id(&e)?;
+    let data = e.update.get_callback_query()?.data.clone().unwrap();
+    let pool = s.get().read().await.get_pool();
+    match data.strip_prefix("org_").unwrap() {
+        "back" => {
+            edit_catalog(
+                &e,
+                &s,
+                CATALOG_TEXT,
+                markup::organizations_markup(pool, id).await,
+            )
+            .await?
+        }
+        vtuber_id => {
+            let vtuber = queries::get_vtuber(pool.clone(), vtuber_id.parse::<i64>()?).await?;
+            edit_catalog(
+                &e,
+                &s,
+                "Выберите отделение",
+                markup::branches_markup(pool, id, &vtuber).await,
+            )
+            .await?
+        }
+    }
+    Ok(Action::Done)
+}
+
+// Handle click on branch button. Show waves of the branch of the vtuber from callback data
+pub async fn branch_handler(
+    e: Event,
+    s: State<MelatoninBotState>,
+) -> Result<Action, anyhow::Error> {
+    let id = get_user_id(&e)?;
+    let data = e.update.get_callback_query()?.data.clone().unwrap();
+    let vtuber_id = data.strip_prefix("branch_").unwrap().parse::<i64>()?;
+    let pool = s.get().read().await.get_pool();
+    let vtuber = queries::get_vtuber(pool.clone(), vtuber_id).await?;
+    edit_catalog(
+        &e,
+        &s,
+        "Выберите волну",
+        markup::waves_markup(pool, id, &vtuber).await,
+    )
+    .await?;
     Ok(Action::Done)
 }
 
-// Handle click on wave button. Show members of the wave
+// Handle click on wave button. Show members of the wave of the vtuber from callback data
 pub async fn wave_handler(e: Event, s: State<MelatoninBotState>) -> Result<Action, anyhow::Error> {
     let id = get_user_id(&e)?;
-    let wave_name = e.update.get_callback_query()?.data.clone().unwrap();
-    let wave_name = wave_name.strip_prefix("wave_").unwrap();
-    e.api
-        .edit_message_text(
-            &EditMessageTextRequest::new(String::from("Выберите втубера"))
+    let data = e.update.get_callback_query()?.data.clone().unwrap();
+    let vtuber_id = data.strip_prefix("wave_").unwrap().parse::<i64>()?;
+    let pool = s.get().read().await.get_pool();
+    let vtuber = queries::get_vtuber(pool.clone(), vtuber_id).await?;
+    // Debut date and description of the wave go before the members
+    let mut text = String::new();
+    if let Some(wave) = queries::get_wave(
+        pool.clone(),
+        &vtuber.organization,
+        &vtuber.branch,
+        &vtuber.wave_name,
+    )
+    .await?
+    {
+        text.push_str(&wave.name);
+        if let Some(debut_date) = wave.debut_date {
+            text.push_str(&format!(" (дебют {})", debut_date.format("%d.%m.%Y")));
+        }
+        if let Some(description) = wave.description {
+            text.push_str(&format!("\n{}", description));
+        }
+        text.push_str("\n\n");
+    }
+    text.push_str("Выберите втубера");
+    edit_catalog(&e, &s, &text, members_markup(pool, id, &vtuber).await).await?;
+    Ok(Action::Done)
+}
+
+// Handle click on member button. Select member or remove selection
+pub async fn member_handler(
+    e: Event,
+    s: State<MelatoninBotState>,
+) -> Result<Action, anyhow::Error> {
+    let id = get_user_id(&e)?;
+    let data = e.update.get_callback_query()?.data.clone().unwrap();
+    let vtuber_id = data.strip_prefix("member_").unwrap().parse::<i64>()?;
+    let pool = s.get().read().await.get_pool();
+    let vtuber = queries::get_vtuber(pool.clone(), vtuber_id).await?;
+    queries::update_user_vtuber(pool.clone(), id, vtuber_id).await?;
+    send(
+        &s,
+        OutboundMessage::EditMarkup(
+            e.update.chat_id()?,
+            EditMessageReplyMarkupRequest::new(markup::members_markup(pool, id, &vtuber).await)
                 .with_chat_id(e.update.chat_id()?)
                 .with_message_id(e.update.message_id()?),
-        )
-        .await?;
-    e.api
-        .edit_message_reply_markup(
-            &EditMessageReplyMarkupRequest::new(
-                members_markup(s.get().read().await.get_pool(), id, String::from(wave_name)).await,
+        ),
+    )
+    .await?;
+    Ok(Action::Done)
+}
+
+// Handle /settings command. Send settings menu
+pub async fn settings_handler(
+    e: Event,
+    s: State<MelatoninBotState>,
+) -> Result<Action, anyhow::Error> {
+    let id = get_user_id(&e)?;
+    send(
+        &s,
+        OutboundMessage::Text(
+            SendMessageRequest::new(e.update.chat_id()?, "Настройки уведомлений")
+                .with_reply_markup(
+                    markup::settings_markup(s.get().read().await.get_pool(), id).await,
+                ),
+        ),
+    )
+    .await?;
+    Ok(Action::Done)
+}
+
+// Handle click on settings menu button. Open selected submenu or return to settings
+pub async fn settings_menu_handler(
+    e: Event,
+    s: State<MelatoninBotState>,
+) -> Result<Action, anyhow::Error> {
+    let id = get_user_id(&e)?;
+    let data = e.update.get_callback_query()?.data.clone().unwrap();
+    let (text, reply_markup) = match data.strip_prefix("settings_").unwrap() {
+        "lead" => (
+            "Выберите, когда напоминать о стриме. Можно выбрать несколько напоминаний",
+            markup::lead_times_markup(s.get().read().await.get_pool(), id).await,
+        ),
+        "tz" => (
+            "Выберите часовой пояс. Другой пояс можно задать командой /timezone, например: /timezone Asia/Almaty",
+            markup::timezone_markup(s.get().read().await.get_pool(), id).await,
+        ),
+        "kind" => (
+            "Выберите, о каких видео напоминать",
+            markup::video_kinds_markup(
+                s.get().read().await.get_pool(),
+                id,
+                s.get().read().await.get_video_kinds(),
+            )
+            .await,
+        ),
+        "schedule" => (
+            "Выберите время, когда присылать расписание стримов на день \
+            (в вашем часовом поясе), и нужен ли обзор недели",
+            markup::digest_markup(s.get().read().await.get_pool(), id).await,
+        ),
+        "topic" => (
+            "Выберите темы стримов. Тема видна в каждом напоминании",
+            markup::topics_markup(s.get().read().await.get_pool(), id).await,
+        ),
+        // Toggle in the settings menu itself, so only buttons are updated
+        "digest" => {
+            let pool = s.get().read().await.get_pool();
+            queries::toggle_user_digest_mode(pool.clone(), id).await?;
+            send(&s, OutboundMessage::EditMarkup(e.update.chat_id()?, EditMessageReplyMarkupRequest::new(markup::settings_markup(pool, id).await)
+                        .with_chat_id(e.update.chat_id()?)
+                        .with_message_id(e.update.message_id()?))).await?;
+            return Ok(Action::Done);
+        }
+        _ => (
+            "Настройки уведомлений",
+            markup::settings_markup(s.get().read().await.get_pool(), id).await,
+        ),
+    };
+    send(
+        &s,
+        OutboundMessage::EditText(
+            e.update.chat_id()?,
+            EditMessageTextRequest::new(String::from(text))
+                .with_chat_id(e.update.chat_id()?)
+                .with_message_id(e.update.message_id()?),
+        ),
+    )
+    .await?;
+    send(
+        &s,
+        OutboundMessage::EditMarkup(
+            e.update.chat_id()?,
+            EditMessageReplyMarkupRequest::new(reply_markup)
+                .with_chat_id(e.update.chat_id()?)
+                .with_message_id(e.update.message_id()?),
+        ),
+    )
+    .await?;
+    Ok(Action::Done)
+}
+
+// Handle click on lead time button. Select or unselect lead time or reminder at the start
+pub async fn lead_time_handler(
+    e: Event,
+    s: State<MelatoninBotState>,
+) -> Result<Action, anyhow::Error> {
+    let id = get_user_id(&e)?;
+    let data = e.update.get_callback_query()?.data.clone().unwrap();
+    let pool = s.get().read().await.get_pool();
+    match data.strip_prefix("lead_").unwrap() {
+        "started" => queries::toggle_user_started_ping(pool.clone(), id).await?,
+        minutes => {
+            let minutes: i64 = minutes.parse()?;
+            if !LEAD_TIME_OPTIONS.contains(&minutes) {
+                return Err(anyhow!("Unknown lead time: {}", minutes));
+            }
+            queries::update_user_lead_time(pool.clone(), id, minutes).await?;
+        }
+    }
+    send(
+        &s,
+        OutboundMessage::EditMarkup(
+            e.update.chat_id()?,
+            EditMessageReplyMarkupRequest::new(markup::lead_times_markup(pool, id).await)
+                .with_chat_id(e.update.chat_id()?)
+                .with_message_id(e.update.message_id()?),
+        ),
+    )
+    .await?;
+    Ok(Action::Done)
+}
+
+// Handle /timezone command. Set timezone from argument or send timezones markup
+pub async fn timezone_handler(
+    e: Event,
+    s: State<MelatoninBotState>,
+) -> Result<Action, anyhow::Error> {
+    let id = get_user_id(&e)?;
+    let pool = s.get().read().await.get_pool();
+    let text = e.update.get_message()?.text.clone().unwrap_or_default();
+    let text = match text.split_once(' ') {
+        Some((_, name)) => match timezone::parse_timezone(name.trim()) {
+            Some(tz) => {
+                queries::update_user_timezone(pool, id, tz.name()).await?;
+                format!("Часовой пояс установлен: {}", tz.name())
+            }
+            None => format!(
+                "Неизвестный часовой пояс: {}. Используйте название из базы IANA, например Europe/Berlin",
+                name.trim()
+            ),
+        },
+        None => {
+            send(&s, OutboundMessage::Text(SendMessageRequest::new(e.update.chat_id()?, "Выберите часовой пояс")
+                        .with_reply_markup(markup::timezone_markup(pool, id).await))).await?;
+            return Ok(Action::Done);
+        }
+    };
+    send(
+        &s,
+        OutboundMessage::Text(SendMessageRequest::new(e.update.chat_id()?, text)),
+    )
+    .await?;
+    Ok(Action::Done)
+}
+
+// Handle click on timezone button. Set selected timezone
+pub async fn timezone_select_handler(
+    e: Event,
+    s: State<MelatoninBotState>,
+) -> Result<Action, anyhow::Error> {
+    let id = get_user_id(&e)?;
+    let data = e.update.get_callback_query()?.data.clone().unwrap();
+    let tz = match timezone::parse_timezone(data.strip_prefix("tz_").unwrap()) {
+        Some(tz) => tz,
+        None => return Err(anyhow!("Unknown timezone: {}", data)),
+    };
+    let pool = s.get().read().await.get_pool();
+    queries::update_user_timezone(pool.clone(), id, tz.name()).await?;
+    send(
+        &s,
+        OutboundMessage::EditMarkup(
+            e.update.chat_id()?,
+            EditMessageReplyMarkupRequest::new(markup::timezone_markup(pool, id).await)
+                .with_chat_id(e.update.chat_id()?)
+                .with_message_id(e.update.message_id()?),
+        ),
+    )
+    .await?;
+    Ok(Action::Done)
+}
+
+// Handle click on video kind button. Select or unselect kind of videos
+pub async fn video_kind_handler(
+    e: Event,
+    s: State<MelatoninBotState>,
+) -> Result<Action, anyhow::Error> {
+    let id = get_user_id(&e)?;
+    let data = e.update.get_callback_query()?.data.clone().unwrap();
+    let kind: VideoKind = data.strip_prefix("kind_").unwrap().parse()?;
+    let pool = s.get().read().await.get_pool();
+    let available = s.get().read().await.get_video_kinds();
+    if !available.contains(&kind) {
+        return Err(anyhow!("Video kind isn't fetched: {}", kind));
+    }
+    queries::update_user_video_kind(pool.clone(), id, kind).await?;
+    send(
+        &s,
+        OutboundMessage::EditMarkup(
+            e.update.chat_id()?,
+            EditMessageReplyMarkupRequest::new(
+                markup::video_kinds_markup(pool, id, available).await,
             )
             .with_chat_id(e.update.chat_id()?)
             .with_message_id(e.update.message_id()?),
+        ),
+    )
+    .await?;
+    Ok(Action::Done)
+}
+
+// Handle click on topic button. Select or unselect topic or switch topic mode
+pub async fn topic_handler(e: Event, s: State<MelatoninBotState>) -> Result<Action, anyhow::Error> {
+    let id = get_user_id(&e)?;
+    let data = e.update.get_callback_query()?.data.clone().unwrap();
+    let pool = s.get().read().await.get_pool();
+    match data.strip_prefix("topic_").unwrap() {
+        "mode" => {
+            let user = queries::get_user(pool.clone(), id).await?;
+            queries::update_user_topic_mode(pool.clone(), id, user.get_topic_mode().toggled())
+                .await?
+        }
+        topic => {
+            if !TOPIC_OPTIONS.contains(&topic) {
+                return Err(anyhow!("Unknown topic: {}", topic));
+            }
+            queries::update_user_topic(pool.clone(), id, topic).await?;
+        }
+    }
+    send(
+        &s,
+        OutboundMessage::EditMarkup(
+            e.update.chat_id()?,
+            EditMessageReplyMarkupRequest::new(markup::topics_markup(pool, id).await)
+                .with_chat_id(e.update.chat_id()?)
+                .with_message_id(e.update.message_id()?),
+        ),
+    )
+    .await?;
+    Ok(Action::Done)
+}
+
+// Handle /schedule command. Send upcoming streams of subscribed vtubers
+pub async fn schedule_handler(
+    e: Event,
+    s: State<MelatoninBotState>,
+) -> Result<Action, anyhow::Error> {
+    let id = get_user_id(&e)?;
+    let pool = s.get().read().await.get_pool();
+    let holodex_api = s.get().read().await.get_holodex_api();
+    let horizon_hours = s.get().read().await.get_schedule_horizon_hours();
+    let user = queries::get_user(pool.clone(), id).await?;
+    let vtubers = queries::get_user_vtubers(pool, id).await?;
+    if vtubers.is_empty() {
+        send(
+            &s,
+            OutboundMessage::Text(SendMessageRequest::new(
+                e.update.chat_id()?,
+                "Вы еще не выбрали втуберов. Выберите их с помощью команды /waves",
+            )),
         )
         .await?;
+        return Ok(Action::Done);
+    }
+    let videos = schedule::fetch_schedule(holodex_api, vtubers, horizon_hours).await?;
+    // Long schedule comes in several messages
+    for text in schedule::render_schedule(&videos, &user.timezone, None) {
+        send(
+            &s,
+            OutboundMessage::Text(
+                SendMessageRequest::new(e.update.chat_id()?, text)
+                    .with_parse_mode(ParseMode::MarkdownV2),
+            ),
+        )
+        .await?;
+    }
     Ok(Action::Done)
 }
 
-// Handle click on member button or 'back' button. Select member or return to waves list
-pub async fn member_handler(
+// Handle click on digest button. Set time of daily digest or switch weekly digest
+pub async fn digest_handler(
     e: Event,
     s: State<MelatoninBotState>,
 ) -> Result<Action, anyhow::Error> {
     let id = get_user_id(&e)?;
     let data = e.update.get_callback_query()?.data.clone().unwrap();
-    let (member_name, wave_name) = data
-        .strip_prefix("member_")
+    let pool = s.get().read().await.get_pool();
+    match data.strip_prefix("digest_").unwrap() {
+        "weekly" => queries::toggle_user_weekly_digest(pool.clone(), id).await?,
+        "time_off" => queries::update_user_digest_time(pool.clone(), id, None).await?,
+        option => {
+            let digest_time = option.strip_prefix("time_").unwrap_or(option);
+            if !DIGEST_TIME_OPTIONS.contains(&digest_time) {
+                return Err(anyhow!("Unknown digest option: {}", option));
+            }
+            queries::update_user_digest_time(pool.clone(), id, Some(digest_time)).await?;
+        }
+    }
+    send(
+        &s,
+        OutboundMessage::EditMarkup(
+            e.update.chat_id()?,
+            EditMessageReplyMarkupRequest::new(markup::digest_markup(pool, id).await)
+                .with_chat_id(e.update.chat_id()?)
+                .with_message_id(e.update.message_id()?),
+        ),
+    )
+    .await?;
+    Ok(Action::Done)
+}
+
+// Check, that user is admin. Otherwise tell user, that command isn't available
+async fn check_admin(e: &Event, s: &State<MelatoninBotState>) -> Result<bool, anyhow::Error> {
+    let id = get_user_id(e)?;
+    if s.get().read().await.get_admin_ids().contains(&id) {
+        return Ok(true);
+    }
+    send(
+        s,
+        OutboundMessage::Text(SendMessageRequest::new(
+            e.update.chat_id()?,
+            "Команда доступна только администраторам",
+        )),
+    )
+    .await?;
+    Ok(false)
+}
+
+// Handle click on 'confirm' button under new channel from holodex sync. Show vtuber in catalog
+pub async fn confirm_vtuber_handler(
+    e: Event,
+    s: State<MelatoninBotState>,
+) -> Result<Action, anyhow::Error> {
+    if !check_admin(&e, &s).await? {
+        return Ok(Action::Done);
+    }
+    let data = e.update.get_callback_query()?.data.clone().unwrap();
+    let vtuber_id = data.strip_prefix("confirm_").unwrap().parse::<i64>()?;
+    let pool = s.get().read().await.get_pool();
+    queries::confirm_vtuber(pool.clone(), vtuber_id).await?;
+    let vtuber = queries::get_vtuber(pool, vtuber_id).await?;
+    send(
+        &s,
+        OutboundMessage::Text(SendMessageRequest::new(
+            e.update.chat_id()?,
+            format!(
+                "Втубер {} {} добавлен в каталог: {} / {} / {}",
+                vtuber.first_name,
+                vtuber.last_name,
+                vtuber.organization,
+                vtuber.branch,
+                vtuber.wave_name
+            ),
+        )),
+    )
+    .await?;
+    Ok(Action::Done)
+}
+
+// Handle /vtuber command: '/vtuber <id> <name> | <branch> | <wave> | <emoji>'.
+// Handle click on 'move' button under channel, that holodex lists in another organization.
+// Move vtuber there, it stays hidden until admin places it with /vtuber
+pub async fn move_vtuber_handler(
+    e: Event,
+    s: State<MelatoninBotState>,
+) -> Result<Action, anyhow::Error> {
+    if !check_admin(&e, &s).await? {
+        return Ok(Action::Done);
+    }
+    let data = e.update.get_callback_query()?.data.clone().unwrap();
+    let (vtuber_id, organization) = data
+        .strip_prefix("move_")
         .unwrap()
-        .split_once(" wave_")
-        .unwrap();
-    match member_name {
-        "back" => {
-            e.api
-            .edit_message_text(
-                &EditMessageTextRequest::new(String::from("Данный бот напоминает о стримах выбранных вами втуберов Nijisanji EN за 15-20 до начала стрима. Выберите волну"))
-                    .with_chat_id(e.update.chat_id()?)
-                    .with_message_id(e.update.message_id()?),
+        .split_once('_')
+        .ok_or(anyhow!("Wrong move data: {}", data))?;
+    let vtuber_id = vtuber_id.parse::<i64>()?;
+    let pool = s.get().read().await.get_pool();
+    queries::move_vtuber_organization(
+        pool.clone(),
+        vtuber_id,
+        organization,
+        catalog_sync::UNSORTED_GROUP,
+    )
+    .await?;
+    let vtuber = queries::get_vtuber(pool, vtuber_id).await?;
+    send(
+        &s,
+        OutboundMessage::Text(SendMessageRequest::new(
+            e.update.chat_id()?,
+            format!(
+                "Втубер {} {} перенесен в {} и скрыт из каталога. \
+                Место в каталоге задается командой:\n\
+                /vtuber {} {} {} | <Отделение> | <Волна> | {}",
+                vtuber.first_name,
+                vtuber.last_name,
+                vtuber.organization,
+                vtuber.id,
+                vtuber.first_name,
+                vtuber.last_name,
+                vtuber.emoji
+            ),
+        )),
+    )
+    .await?;
+    Ok(Action::Done)
+}
+
+// Set place of vtuber in the catalog and confirm it
+pub async fn vtuber_handler(
+    e: Event,
+    s: State<MelatoninBotState>,
+) -> Result<Action, anyhow::Error> {
+    if !check_admin(&e, &s).await? {
+        return Ok(Action::Done);
+    }
+    let pool = s.get().read().await.get_pool();
+    let text = e.update.get_message()?.text.clone().unwrap_or_default();
+    let args = text.split_once(' ').map(|x| x.1).unwrap_or_default();
+    let fields = args.split('|').map(|x| x.trim()).collect::<Vec<_>>();
+    let parsed = match fields[..] {
+        [name, branch, wave_name, emoji] => match name.split_once(' ') {
+            Some((id, name)) => id.parse::<i64>().ok().map(|id| {
+                let (first_name, last_name) = name.trim().split_once(' ').unwrap_or((name, ""));
+                (id, first_name, last_name, branch, wave_name, emoji)
+            }),
+            None => None,
+        },
+        _ => None,
+    };
+    let text = match parsed {
+        Some((id, first_name, last_name, branch, wave_name, emoji))
+            if !first_name.is_empty() && !branch.is_empty() && !wave_name.is_empty() =>
+        {
+            let mut vtuber = queries::get_vtuber(pool.clone(), id).await?;
+            vtuber.first_name = String::from(first_name);
+            vtuber.last_name = String::from(last_name);
+            vtuber.branch = String::from(branch);
+            vtuber.wave_name = String::from(wave_name);
+            vtuber.emoji = String::from(emoji);
+            queries::update_vtuber_catalog_info(pool, &vtuber).await?;
+            format!(
+                "Втубер {} {} {} добавлен в каталог: {} / {} / {}",
+                vtuber.first_name,
+                vtuber.last_name,
+                vtuber.emoji,
+                vtuber.organization,
+                vtuber.branch,
+                vtuber.wave_name
             )
-            .await?;
-            e.api
-                .edit_message_reply_markup(
-                    &EditMessageReplyMarkupRequest::new(
-                        markup::waves_markup(s.get().read().await.get_pool(), id).await,
-                    )
-                    .with_chat_id(e.update.chat_id()?)
-                    .with_message_id(e.update.message_id()?),
-                )
-                .await?;
         }
-        _ => {
-            let (first_name, last_name) = member_name.split_once(" ").unwrap();
-            let pool = s.get().read().await.get_pool();
-            let vtuber_id = sqlx::query!(
-                "SELECT id FROM vtuber WHERE first_name = ? AND last_name = ?",
-                first_name,
-                last_name
+        _ => String::from(
+            "Формат команды: /vtuber <id> <Имя Фамилия> | <Отделение> | <Волна> | <Эмодзи>",
+        ),
+    };
+    send(
+        &s,
+        OutboundMessage::Text(SendMessageRequest::new(e.update.chat_id()?, text)),
+    )
+    .await?;
+    Ok(Action::Done)
+}
+
+// Handle /status command: '/status <id> <active|graduated|hiatus>'. Set lifecycle status
+// of vtuber, subscribers get notice about it
+pub async fn vtuber_status_handler(
+    e: Event,
+    s: State<MelatoninBotState>,
+) -> Result<Action, anyhow::Error> {
+    if !check_admin(&e, &s).await? {
+        return Ok(Action::Done);
+    }
+    let pool = s.get().read().await.get_pool();
+    let text = e.update.get_message()?.text.clone().unwrap_or_default();
+    let args = text.split_whitespace().skip(1).collect::<Vec<_>>();
+    let parsed = match args[..] {
+        [id, status] => match (id.parse::<i64>(), status.parse::<VtuberStatus>()) {
+            (Ok(id), Ok(status)) => Some((id, status)),
+            _ => None,
+        },
+        _ => None,
+    };
+    let text = match parsed {
+        Some((id, status)) => {
+            let vtuber = queries::get_vtuber(pool.clone(), id).await?;
+            queries::update_vtuber_status(pool, id, status).await?;
+            format!(
+                "Статус втубера {} {}: {}",
+                vtuber.first_name, vtuber.last_name, status
             )
-            .fetch_one(&pool)
-            .await
-            .unwrap()
-            .id;
-            queries::update_user_vtuber(pool, id, vtuber_id).await?;
-            e.api
-                .edit_message_reply_markup(
-                    &EditMessageReplyMarkupRequest::new(
-                        markup::members_markup(
-                            s.get().read().await.get_pool(),
-                            id,
-                            String::from(wave_name),
-                        )
-                        .await,
-                    )
-                    .with_chat_id(e.update.chat_id()?)
-                    .with_message_id(e.update.message_id()?),
-                )
-                .await?;
         }
-    }
+        None => String::from("Формат команды: /status <id> <active|graduated|hiatus>"),
+    };
+    send(
+        &s,
+        OutboundMessage::Text(SendMessageRequest::new(e.update.chat_id()?, text)),
+    )
+    .await?;
+    Ok(Action::Done)
+}
+
+// Handle click on 'unsubscribe' button under status notice. Remove vtuber from user list
+pub async fn unsubscribe_handler(
+    e: Event,
+    s: State<MelatoninBotState>,
+) -> Result<Action, anyhow::Error> {
+    let id = get_user_id(&e)?;
+    let data = e.update.get_callback_query()?.data.clone().unwrap();
+    let vtuber_id = data.strip_prefix("unsub_").unwrap().parse::<i64>()?;
+    let pool = s.get().read().await.get_pool();
+    let vtuber = queries::get_vtuber(pool.clone(), vtuber_id).await?;
+    queries::delete_user_vtuber(pool, id, vtuber_id).await?;
+    send(
+        &s,
+        OutboundMessage::EditText(
+            e.update.chat_id()?,
+            EditMessageTextRequest::new(format!(
+                "{} {} {} больше нет в вашем списке",
+                vtuber.first_name, vtuber.last_name, vtuber.emoji
+            ))
+            .with_chat_id(e.update.chat_id()?)
+            .with_message_id(e.update.message_id()?),
+        ),
+    )
+    .await?;
     Ok(Action::Done)
 }
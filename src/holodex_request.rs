@@ -0,0 +1,81 @@
+use std::sync::Arc;
+use tokio::sync::Semaphore;
+use tokio::time::{timeout, Duration};
+
+// How long to wait for holodex response
+pub const HOLODEX_TIMEOUT_SEC: u64 = 30;
+
+// Max blocking threads busy with holodex requests at once
+const MAX_IN_FLIGHT: usize = 4;
+
+// Slots for blocking threads. Slot is freed, when the thread is done, not when caller stops
+// waiting, so requests hanging after timeout can't pile up on the blocking pool
+static IN_FLIGHT: Semaphore = Semaphore::const_new(MAX_IN_FLIGHT);
+
+// Run request of the blocking holodex client on the blocking pool, so runtime workers stay
+// free for bot commands. Caller stops waiting after timeout - the blocking thread can't be
+// interrupted, it keeps its slot till http client gives up on its own. New requests are
+// skipped, while all slots are taken by hanging ones
+pub async fn request<T, F>(holodex_api: Arc<holodex::Client>, f: F) -> anyhow::Result<T>
+where
+    T: Send + 'static,
+    F: FnOnce(&holodex::Client) -> anyhow::Result<T> + Send + 'static,
+{
+    let permit = match timeout(
+        Duration::from_secs(HOLODEX_TIMEOUT_SEC),
+        IN_FLIGHT.acquire(),
+    )
+    .await
+    {
+        Ok(permit) => permit?,
+        Err(_) => {
+            return Err(anyhow::anyhow!(
+                "Holodex request skipped: {} previous requests are still running",
+                MAX_IN_FLIGHT
+            ))
+        }
+    };
+    let task = tokio::task::spawn_blocking(move || {
+        let _permit = permit;
+        f(&holodex_api)
+    });
+    match timeout(Duration::from_secs(HOLODEX_TIMEOUT_SEC), task).await {
+        Ok(res) => res?,
+        Err(_) => Err(anyhow::anyhow!(
+            "Holodex request timed out after {} seconds",
+            HOLODEX_TIMEOUT_SEC
+        )),
+    }
+}
+
+// Videos per holodex page
+pub const PAGE_SIZE: u32 = 50;
+
+// Safety limit of pages for one query
+const MAX_PAGES: u32 = 20;
+
+// Request videos page by page, until the last (incomplete) page. Filter is built for
+// every page by its offset
+pub async fn request_all_videos<F>(
+    holodex_api: Arc<holodex::Client>,
+    build_filter: F,
+) -> anyhow::Result<Vec<holodex::model::Video>>
+where
+    F: Fn(u32) -> holodex::model::builders::VideoFilter,
+{
+    let mut videos = Vec::new();
+    for page in 0..MAX_PAGES {
+        let filter = build_filter(page * PAGE_SIZE);
+        let page_videos = request(holodex_api.clone(), move |api| Ok(api.videos(&filter)?)).await?;
+        let is_last = (page_videos.len() as u32) < PAGE_SIZE;
+        videos.extend(page_videos);
+        if is_last {
+            return Ok(videos);
+        }
+    }
+    log::warn!(
+        "Holodex query has more than {} videos, the rest is skipped",
+        MAX_PAGES * PAGE_SIZE
+    );
+    Ok(videos)
+}
@@ -9,14 +9,27 @@ use mobot::handler::{BotState, State};
 use std::sync::Arc;
 
 mod bot_init;
+mod catalog;
+mod catalog_sync;
+mod collab;
 mod config;
+mod digest;
 mod handlers;
+mod holodex_request;
 mod main_client;
 mod markup;
+mod outbox;
 mod queries;
+mod reminder;
 mod reported_stream;
+mod schedule;
+mod send_queue;
+mod timezone;
+mod topic;
 mod user;
+mod video_kind;
 mod vtuber;
+mod vtuber_status;
 
 // Basic error handler
 async fn error_handler<S: BotState>(_: Arc<API>, _: i64, _: State<S>, err: anyhow::Error) {
@@ -24,7 +37,7 @@ async fn error_handler<S: BotState>(_: Arc<API>, _: i64, _: State<S>, err: anyho
 }
 
 // Fetch streams in interval and notify users, when some stream will start soon
-async fn notify_users(main_client: MainClient, timer_duration_sec: u64) {
+async fn notify_users(main_client: Arc<MainClient>, timer_duration_sec: u64) {
     let mut interval = tokio::time::interval(std::time::Duration::from_secs(timer_duration_sec));
     loop {
         interval.tick().await;
@@ -39,7 +52,12 @@ async fn notify_users(main_client: MainClient, timer_duration_sec: u64) {
             }
         };
         info!("Final fetched amount: {}", videos.len());
-        for stream in videos {
+        // Detect rescheduled and cancelled streams
+        if let Err(e) = main_client.check_reported_streams(&videos).await {
+            error!("Error during checking reported streams: {}", e);
+            main_client.send_alert(e).await;
+        }
+        for stream in &videos {
             match stream.video.channel.clone() {
                 holodex::model::VideoChannel::Id(id) => debug!("Fetched stream(id): {}", id),
                 holodex::model::VideoChannel::Min(min_info) => debug!(
@@ -50,16 +68,63 @@ async fn notify_users(main_client: MainClient, timer_duration_sec: u64) {
                         .unwrap_or(String::from("no english_name"))
                 ),
             }
+        }
+        main_client.enqueue_notifications(&videos).await;
+        if let Err(e) = main_client.clean_reported_streams().await {
+            error!("Error during cleaning reported streams: {}", e);
+            main_client.send_alert(e).await;
+        }
+    }
+}
 
-            match queries::is_stream_reported(main_client.get_pool(), &stream.video)
-                .await
-                .unwrap()
-            {
-                Some(_) => (),
-                None => main_client.send_notification(stream).await,
-            }
+// Deliver notifications from the outbox. Entries survive restarts, so nothing is lost
+// between enqueueing and sending
+async fn drain_outbox(main_client: Arc<MainClient>) {
+    let mut interval = tokio::time::interval(std::time::Duration::from_secs(5));
+    loop {
+        interval.tick().await;
+        if let Err(e) = main_client.clone().drain_outbox().await {
+            error!("Error during draining outbox: {}", e);
+            main_client.send_alert(e).await;
+        }
+    }
+}
+
+// Check every minute, whether it's time to send daily or weekly schedule digests
+async fn push_digests(main_client: Arc<MainClient>) {
+    let mut interval = tokio::time::interval(std::time::Duration::from_secs(60));
+    loop {
+        interval.tick().await;
+        if let Err(e) = digest::send_digests(&main_client).await {
+            error!("Error during sending digests: {}", e);
+            main_client.send_alert(e).await;
+        }
+    }
+}
+
+// Check every minute, whether subscribers of graduated vtubers or vtubers on hiatus need
+// a notice. Runs apart from fetching, so big notice doesn't delay reminders
+async fn send_status_notices(main_client: Arc<MainClient>) {
+    let mut interval = tokio::time::interval(std::time::Duration::from_secs(60));
+    loop {
+        interval.tick().await;
+        if let Err(e) = main_client.clone().send_status_notices().await {
+            error!("Error during sending status notices: {}", e);
+            main_client.send_alert(e).await;
+        }
+    }
+}
+
+// Sync catalog with holodex in interval: add new channels and notify admins about changes
+async fn sync_catalog(main_client: Arc<MainClient>, interval_hours: u64) {
+    let mut interval = tokio::time::interval(std::time::Duration::from_secs(interval_hours * 3600));
+    loop {
+        interval.tick().await;
+        info!("Syncing catalog");
+        if let Err(e) = catalog_sync::sync_catalog(&main_client).await {
+            error!("Error during syncing catalog: {}", e);
+            main_client.send_alert(e).await;
         }
-        main_client.clean_reported_streams().await;
     }
 }
 
@@ -70,9 +135,15 @@ async fn notify_users(main_client: MainClient, timer_duration_sec: u64) {
 async fn main() -> Result<(), anyhow::Error> {
     // Create bot state
     let bot_state = bot_init::init_app().await?;
-    let holodex_api_key = bot_state.get_holodex_api_key();
+    let holodex_api = bot_state.get_holodex_api();
+    let send_queue = bot_state.get_send_queue();
     let timer_duration_sec = bot_state.get_timer_duration_sec();
     let monitoring_ip = bot_state.get_monitoring_ip();
+    let reschedule_threshold_min = bot_state.get_reschedule_threshold_min();
+    let video_kinds = bot_state.get_video_kinds();
+    let send_parallelism = bot_state.get_send_parallelism();
+    let admin_ids = bot_state.get_admin_ids();
+    let catalog_sync_hours = bot_state.get_catalog_sync_hours();
     let alert_client = Arc::new(reqwest::Client::new());
 
     // Create client for mobot
@@ -84,7 +155,19 @@ async fn main() -> Result<(), anyhow::Error> {
     let commands = vec![
         BotCommand {
             command: "waves".into(),
-            description: "Show list of waves".into(),
+            description: "Show catalog of vtubers".into(),
+        },
+        BotCommand {
+            command: "schedule".into(),
+            description: "Upcoming streams of selected vtubers".into(),
+        },
+        BotCommand {
+            command: "settings".into(),
+            description: "Notification settings".into(),
+        },
+        BotCommand {
+            command: "timezone".into(),
+            description: "Set timezone, e.g. /timezone Europe/Berlin".into(),
         },
         BotCommand {
             command: "about".into(),
@@ -107,12 +190,16 @@ async fn main() -> Result<(), anyhow::Error> {
     info!("Setuped router");
 
     // Create client for fetching videos and notifying users
-    let main_client = main_client::MainClient::new(
-        router.api.clone(),
-        Arc::new(holodex::Client::new(&holodex_api_key)?),
+    let main_client = Arc::new(main_client::MainClient::new(
+        send_queue,
+        holodex_api,
         monitoring_ip,
         alert_client.clone(),
-    );
+        reschedule_threshold_min,
+        video_kinds,
+        send_parallelism,
+        admin_ids,
+    ));
 
     // Add routes
     router
@@ -142,6 +229,24 @@ async fn main() -> Result<(), anyhow::Error> {
             mobot::Route::Message(mobot::Matcher::BotCommand(String::from("waves"))),
             crate::handlers::info_handler,
         );
+    router
+        .add_route(
+            mobot::Route::CallbackQuery(mobot::Matcher::Prefix(String::from("org_"))),
+            |e, s| async move { report_action(e, s, "organization_request").await },
+        )
+        .add_route(
+            mobot::Route::CallbackQuery(mobot::Matcher::Prefix(String::from("org_"))),
+            crate::handlers::organization_handler,
+        );
+    router
+        .add_route(
+            mobot::Route::CallbackQuery(mobot::Matcher::Prefix(String::from("branch_"))),
+            |e, s| async move { report_action(e, s, "branch_request").await },
+        )
+        .add_route(
+            mobot::Route::CallbackQuery(mobot::Matcher::Prefix(String::from("branch_"))),
+            crate::handlers::branch_handler,
+        );
     router
         .add_route(
             mobot::Route::CallbackQuery(mobot::Matcher::Prefix(String::from("wave_"))),
@@ -160,10 +265,159 @@ async fn main() -> Result<(), anyhow::Error> {
             mobot::Route::CallbackQuery(mobot::Matcher::Prefix(String::from("member_"))),
             crate::handlers::member_handler,
         );
+    router
+        .add_route(
+            mobot::Route::Message(mobot::Matcher::BotCommand(String::from("schedule"))),
+            |e, s| async move { report_action(e, s, "schedule_handler").await },
+        )
+        .add_route(
+            mobot::Route::Message(mobot::Matcher::BotCommand(String::from("schedule"))),
+            crate::handlers::schedule_handler,
+        );
+    router
+        .add_route(
+            mobot::Route::Message(mobot::Matcher::BotCommand(String::from("settings"))),
+            |e, s| async move { report_action(e, s, "settings_handler").await },
+        )
+        .add_route(
+            mobot::Route::Message(mobot::Matcher::BotCommand(String::from("settings"))),
+            crate::handlers::settings_handler,
+        );
+    router
+        .add_route(
+            mobot::Route::CallbackQuery(mobot::Matcher::Prefix(String::from("settings_"))),
+            |e, s| async move { report_action(e, s, "settings_request").await },
+        )
+        .add_route(
+            mobot::Route::CallbackQuery(mobot::Matcher::Prefix(String::from("settings_"))),
+            crate::handlers::settings_menu_handler,
+        );
+    router
+        .add_route(
+            mobot::Route::CallbackQuery(mobot::Matcher::Prefix(String::from("lead_"))),
+            |e, s| async move { report_action(e, s, "lead_time_request").await },
+        )
+        .add_route(
+            mobot::Route::CallbackQuery(mobot::Matcher::Prefix(String::from("lead_"))),
+            crate::handlers::lead_time_handler,
+        );
+    router
+        .add_route(
+            mobot::Route::Message(mobot::Matcher::BotCommand(String::from("timezone"))),
+            |e, s| async move { report_action(e, s, "timezone_handler").await },
+        )
+        .add_route(
+            mobot::Route::Message(mobot::Matcher::BotCommand(String::from("timezone"))),
+            crate::handlers::timezone_handler,
+        );
+    router
+        .add_route(
+            mobot::Route::CallbackQuery(mobot::Matcher::Prefix(String::from("tz_"))),
+            |e, s| async move { report_action(e, s, "timezone_request").await },
+        )
+        .add_route(
+            mobot::Route::CallbackQuery(mobot::Matcher::Prefix(String::from("tz_"))),
+            crate::handlers::timezone_select_handler,
+        );
+    router
+        .add_route(
+            mobot::Route::CallbackQuery(mobot::Matcher::Prefix(String::from("kind_"))),
+            |e, s| async move { report_action(e, s, "video_kind_request").await },
+        )
+        .add_route(
+            mobot::Route::CallbackQuery(mobot::Matcher::Prefix(String::from("kind_"))),
+            crate::handlers::video_kind_handler,
+        );
+    router
+        .add_route(
+            mobot::Route::CallbackQuery(mobot::Matcher::Prefix(String::from("topic_"))),
+            |e, s| async move { report_action(e, s, "topic_request").await },
+        )
+        .add_route(
+            mobot::Route::CallbackQuery(mobot::Matcher::Prefix(String::from("topic_"))),
+            crate::handlers::topic_handler,
+        );
+    router
+        .add_route(
+            mobot::Route::CallbackQuery(mobot::Matcher::Prefix(String::from("digest_"))),
+            |e, s| async move { report_action(e, s, "digest_request").await },
+        )
+        .add_route(
+            mobot::Route::CallbackQuery(mobot::Matcher::Prefix(String::from("digest_"))),
+            crate::handlers::digest_handler,
+        );
+    router
+        .add_route(
+            mobot::Route::Message(mobot::Matcher::BotCommand(String::from("vtuber"))),
+            |e, s| async move { report_action(e, s, "vtuber_handler").await },
+        )
+        .add_route(
+            mobot::Route::Message(mobot::Matcher::BotCommand(String::from("vtuber"))),
+            crate::handlers::vtuber_handler,
+        );
+    router
+        .add_route(
+            mobot::Route::MyChatMember(mobot::Matcher::Any),
+            |e, s| async move { report_action(e, s, "my_chat_member").await },
+        )
+        .add_route(
+            mobot::Route::MyChatMember(mobot::Matcher::Any),
+            crate::handlers::my_chat_member_handler,
+        );
+    router
+        .add_route(
+            mobot::Route::CallbackQuery(mobot::Matcher::Prefix(String::from("confirm_"))),
+            |e, s| async move { report_action(e, s, "confirm_request").await },
+        )
+        .add_route(
+            mobot::Route::CallbackQuery(mobot::Matcher::Prefix(String::from("confirm_"))),
+            crate::handlers::confirm_vtuber_handler,
+        );
+    router
+        .add_route(
+            mobot::Route::CallbackQuery(mobot::Matcher::Prefix(String::from("move_"))),
+            |e, s| async move { report_action(e, s, "move_request").await },
+        )
+        .add_route(
+            mobot::Route::CallbackQuery(mobot::Matcher::Prefix(String::from("move_"))),
+            crate::handlers::move_vtuber_handler,
+        );
+    router
+        .add_route(
+            mobot::Route::Message(mobot::Matcher::BotCommand(String::from("status"))),
+            |e, s| async move { report_action(e, s, "vtuber_status_handler").await },
+        )
+        .add_route(
+            mobot::Route::Message(mobot::Matcher::BotCommand(String::from("status"))),
+            crate::handlers::vtuber_status_handler,
+        );
+    router
+        .add_route(
+            mobot::Route::CallbackQuery(mobot::Matcher::Prefix(String::from("unsub_"))),
+            |e, s| async move { report_action(e, s, "unsubscribe_request").await },
+        )
+        .add_route(
+            mobot::Route::CallbackQuery(mobot::Matcher::Prefix(String::from("unsub_"))),
+            crate::handlers::unsubscribe_handler,
+        );
     info!("Routes were added");
     // Start notify-thread
-    tokio::spawn(notify_users(main_client, timer_duration_sec));
+    tokio::spawn(notify_users(main_client.clone(), timer_duration_sec));
     info!("Fetching thread was started");
+    // Start outbox-thread
+    tokio::spawn(drain_outbox(main_client.clone()));
+    info!("Outbox thread was started");
+    // Start catalog-sync-thread
+    if catalog_sync_hours > 0 {
+        tokio::spawn(sync_catalog(main_client.clone(), catalog_sync_hours));
+        info!("Catalog sync thread was started");
+    }
+    // Start status-notice-thread
+    tokio::spawn(send_status_notices(main_client.clone()));
+    info!("Status notice thread was started");
+    // Start digest-thread
+    tokio::spawn(push_digests(main_client));
+    info!("Digest thread was started");
     // Start bot
     info!("Bot was started");
     router.start().await;
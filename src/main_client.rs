@@ -1,19 +1,31 @@
-use crate::{config::MyPool, reported_stream::ReportedStream};
+use crate::collab;
+use crate::holodex_request;
+use crate::outbox::{self, OutboxEntry, OutboxItem, OutboxMessage};
+use crate::reminder::{
+    self, ReminderStage, LATE_START_HOURS, LEAD_TIME_OPTIONS, LIVE_GRACE_MINUTES,
+};
+use crate::send_queue::{self, OutboundMessage, SendQueue};
+use crate::topic;
+use crate::user::User;
+use crate::video_kind::VideoKind;
+use crate::vtuber::Vtuber;
+use crate::{config::MyPool, queries, reported_stream::ReportedStream, timezone};
 use chrono;
-use chrono::Timelike;
-use log::debug;
-use mobot::api::{ParseMode, SendPhotoRequest};
+use log::{error, info, warn};
+use mobot::api::{self, ParseMode, SendMessageRequest, SendPhotoRequest};
 use sqlx::{Pool, Sqlite};
+use std::future::Future;
 use std::sync::Arc;
+use tokio::sync::Semaphore;
+use tokio::task::JoinSet;
 
 use holodex::model::{
-    builders::VideoFilterBuilder, ExtraVideoInfo, Language, Organisation, VideoSortingCriteria,
-    VideoType,
+    builders::VideoFilterBuilder, ChannelId, ExtraVideoInfo, VideoChannel, VideoId, VideoStatus,
 };
 
 pub struct MainClient {
-    // Telegram api client
-    pub tg_api: Arc<mobot::API>,
+    // Rate-limited queue for outbound messages
+    pub send_queue: SendQueue,
     // Holodex api client
     pub holodex_api: Arc<holodex::Client>,
     // Global sql connection pool
@@ -22,15 +34,68 @@ pub struct MainClient {
     pub monitoring_ip: String,
     // Client for requests
     pub alert_client: Arc<reqwest::Client>,
+    // Minimal shift of stream start (in minutes) to notify users about reschedule
+    reschedule_threshold_min: i64,
+    // Kinds of videos to fetch
+    video_kinds: Vec<VideoKind>,
+    // Limit of users, notified at the same time
+    send_limit: Arc<Semaphore>,
+    // Telegram ids of admins, who confirm changes of the catalog
+    pub admin_ids: Vec<i64>,
 }
 
 pub struct VtuberVideo {
-    // Vtuber, associated with video
-    pub vtuber: crate::vtuber::Vtuber,
+    // Vtuber, that owns the channel of the video. None, if the channel isn't in db
+    pub host: Option<Vtuber>,
+    // Vtubers from db, that are mentioned in the video
+    pub guests: Vec<Vtuber>,
     // Video of the vtuber
     pub video: holodex::model::Video,
 }
 
+impl VtuberVideo {
+    // Vtuber, the stream is reported for: channel owner or first guest. Notices about the
+    // stream name its channel, not this vtuber
+    pub fn main_vtuber(&self) -> &Vtuber {
+        match &self.host {
+            Some(host) => host,
+            None => &self.guests[0],
+        }
+    }
+    // Host and guests of the stream
+    pub fn participants(&self) -> Vec<&Vtuber> {
+        self.host.iter().chain(self.guests.iter()).collect()
+    }
+    // Name of the channel, where the stream takes place
+    pub fn channel_name(&self) -> String {
+        match (&self.host, &self.video.channel) {
+            (Some(host), _) => format!("{} {}", host.first_name, host.last_name),
+            (None, VideoChannel::Min(min_info)) => min_info
+                .english_name
+                .clone()
+                .unwrap_or(min_info.name.clone()),
+            (None, VideoChannel::Id(id)) => id.to_string(),
+        }
+    }
+}
+
+// Reminder about the stream for one user
+pub struct Notification<'a> {
+    // Stream to remind about
+    pub stream: &'a VtuberVideo,
+    // Vtubers of the stream, that user follows
+    pub followed: Vec<Vtuber>,
+    // Reminder stage to build message from
+    pub stage: ReminderStage,
+    // All due stages, that are delivered with the message
+    pub due: Vec<ReminderStage>,
+    // Minutes left until scheduled start
+    pub time_left: i64,
+}
+
+// Channels in one request for live and upcoming videos
+const CHANNEL_BATCH_SIZE: usize = 50;
+
 #[derive(serde::Serialize)]
 struct AlertRequest {
     pub from: String,
@@ -40,92 +105,361 @@ struct AlertRequest {
 
 impl MainClient {
     pub fn new(
-        mobot_client: Arc<mobot::API>,
+        send_queue: SendQueue,
         holodex_client: Arc<holodex::Client>,
         monitoring_ip: String,
         alert_client: Arc<reqwest::Client>,
+        reschedule_threshold_min: i64,
+        video_kinds: Vec<VideoKind>,
+        send_parallelism: usize,
+        admin_ids: Vec<i64>,
     ) -> Self {
         Self {
-            tg_api: mobot_client,
+            send_queue: send_queue,
             holodex_api: holodex_client,
             sql_pool: MyPool::default(),
             monitoring_ip: monitoring_ip,
             alert_client: alert_client,
+            reschedule_threshold_min: reschedule_threshold_min,
+            video_kinds: video_kinds,
+            send_limit: Arc::new(Semaphore::new(send_parallelism)),
+            admin_ids: admin_ids,
         }
     }
     // Get sql pool
     pub fn get_pool(&self) -> Pool<Sqlite> {
         self.sql_pool.0.clone()
     }
-    // Get stream, that will start soon
-    pub fn get_videos(&self) -> anyhow::Result<Vec<holodex::model::Video>> {
-        let filter = VideoFilterBuilder::new()
-            .organisation(Organisation::Nijisanji)
-            .language(&[Language::English])
-            .video_type(VideoType::Stream)
-            .max_upcoming_hours(1)
-            .include(&[
-                ExtraVideoInfo::Description,
-                ExtraVideoInfo::ChannelStats,
-                ExtraVideoInfo::LiveInfo,
-            ])
-            .sort_by(VideoSortingCriteria::StartScheduled)
-            .status(&[holodex::model::VideoStatus::Upcoming])
-            .limit(50)
-            .build();
-        Ok(self
-            .holodex_api
-            .videos(&filter)?
-            .into_iter()
-            .filter(|x| {
-                let is_passed = match x.live_info.start_scheduled {
-                    Some(start) => chrono::Utc::now().naive_utc() > start.naive_utc(),
-                    None => true,
-                };
-                !is_passed
-                    && x.available_at.naive_utc() - chrono::Utc::now().naive_utc()
-                        < chrono::Duration::minutes(22)
+    // Get videos of configured kinds, that will start soon on channels of vtubers or with
+    // them as guests. Channels are requested in batches from holodex endpoint for a set of
+    // channels, it also returns collabs, where the channels are mentioned. Batch, that
+    // failed, is skipped, so other channels still get their reminders
+    pub async fn get_videos(
+        &self,
+        vtubers: &[Vtuber],
+    ) -> anyhow::Result<Vec<holodex::model::Video>> {
+        let channel_ids = vtubers
+            .iter()
+            .filter_map(|x| match x.youtube_channel_id.parse::<ChannelId>() {
+                Ok(id) => Some(id),
+                Err(e) => {
+                    warn!(
+                        "Bad channel id {} of vtuber {}: {}",
+                        x.youtube_channel_id, x.id, e
+                    );
+                    None
+                }
+            })
+            .collect::<Vec<_>>();
+        let batches = channel_ids.chunks(CHANNEL_BATCH_SIZE).collect::<Vec<_>>();
+        let mut found: Vec<holodex::model::Video> = Vec::new();
+        let mut failed = 0;
+        let mut last_error = None;
+        for batch in &batches {
+            let batch = batch.to_vec();
+            let batch_len = batch.len();
+            let res = holodex_request::request(self.holodex_api.clone(), move |api| {
+                Ok(api.live_from_channels(&batch)?)
             })
+            .await;
+            match res {
+                Ok(videos) => {
+                    for video in videos {
+                        if !found
+                            .iter()
+                            .any(|x| x.id.to_string() == video.id.to_string())
+                        {
+                            found.push(video);
+                        }
+                    }
+                }
+                Err(e) => {
+                    error!("Batch of {} channels is skipped: {}", batch_len, e);
+                    failed += 1;
+                    last_error = Some(e);
+                }
+            }
+        }
+        if let (true, Some(e)) = (failed == batches.len(), last_error) {
+            return Err(e);
+        }
+        // Endpoint for a set of channels has no description, mentions and full live info, so
+        // videos, that can be within the window, are requested again by ids
+        let ids = found
+            .into_iter()
+            .filter(|x| matches!(x.status, VideoStatus::Live) || self.is_in_window(x))
+            .map(|x| x.id)
+            .collect::<Vec<_>>();
+        let mut videos = Vec::new();
+        for chunk in ids.chunks(holodex_request::PAGE_SIZE as usize) {
+            match self.get_videos_by_id(chunk).await {
+                Ok(chunk_videos) => videos.extend(chunk_videos),
+                Err(e) => error!("Details of {} videos are skipped: {}", chunk.len(), e),
+            }
+        }
+        Ok(videos
+            .into_iter()
+            .filter(|x| self.is_in_window(x))
+            .filter(|x| self.video_kinds.contains(&VideoKind::of(x)))
             .collect())
     }
+    // Check, that video needs reminders now
+    fn is_in_window(&self, video: &holodex::model::Video) -> bool {
+        let time_now = chrono::Utc::now().naive_utc();
+        match video.status {
+            // Live stream is kept for a while for 'started' reminder
+            VideoStatus::Live => match video.live_info.start_actual {
+                Some(start) => {
+                    time_now - start.naive_utc() < chrono::Duration::minutes(LIVE_GRACE_MINUTES)
+                }
+                None => false,
+            },
+            // Upcoming stream is kept within the longest lead time users can pick and until
+            // it goes live, even if it starts late
+            VideoStatus::Upcoming => {
+                let max_lead_time = *LEAD_TIME_OPTIONS.iter().max().unwrap();
+                let time_left = video.available_at.naive_utc() - time_now;
+                time_left <= chrono::Duration::minutes(max_lead_time)
+                    && time_left > -chrono::Duration::hours(LATE_START_HOURS)
+            }
+            _ => false,
+        }
+    }
+    // Get videos by their ids
+    pub async fn get_videos_by_id(
+        &self,
+        ids: &[VideoId],
+    ) -> anyhow::Result<Vec<holodex::model::Video>> {
+        // Ids are requested in chunks of page size
+        let mut videos = Vec::new();
+        for chunk in ids.chunks(holodex_request::PAGE_SIZE as usize) {
+            let filter = VideoFilterBuilder::new()
+                .id(chunk)
+                .include(&[
+                    ExtraVideoInfo::Description,
+                    ExtraVideoInfo::ChannelStats,
+                    ExtraVideoInfo::LiveInfo,
+                    ExtraVideoInfo::Mentions,
+                ])
+                .limit(chunk.len() as u32)
+                .build();
+            videos.extend(
+                holodex_request::request(self.holodex_api.clone(), move |api| {
+                    Ok(api.videos(&filter)?)
+                })
+                .await?,
+            );
+        }
+        Ok(videos)
+    }
+    // Compare reported streams with fresh data from holodex and notify users about changes
+    pub async fn check_reported_streams(&self, fetched: &[VtuberVideo]) -> anyhow::Result<()> {
+        let reported_streams = queries::get_reported_streams(self.get_pool()).await?;
+        // Streams, that left fetching window (e.g. were moved to another day), are requested by id
+        let missing_ids = reported_streams
+            .iter()
+            .filter(|x| !fetched.iter().any(|y| y.video.id.to_string() == x.video_id))
+            .filter_map(|x| x.video_id.parse::<VideoId>().ok())
+            .collect::<Vec<_>>();
+        let requested = self.get_videos_by_id(&missing_ids).await?;
+        for mut stream in reported_streams {
+            let video = fetched
+                .iter()
+                .map(|x| &x.video)
+                .chain(requested.iter())
+                .find(|x| x.id.to_string() == stream.video_id);
+            if video.is_some() && stream.missed_polls > 0 {
+                queries::update_missed_polls(self.get_pool(), stream.id, 0).await?;
+            }
+            match video {
+                // Stream was deleted or holodex dropped it from this response. It's cancelled
+                // only after several polls in a row without it
+                None => match stream.miss() {
+                    true => self.enqueue_cancel_notice(&stream, None).await?,
+                    false => {
+                        queries::update_missed_polls(
+                            self.get_pool(),
+                            stream.id,
+                            stream.missed_polls,
+                        )
+                        .await?
+                    }
+                },
+                Some(video) if matches!(video.status, VideoStatus::Missing) => {
+                    self.enqueue_cancel_notice(&stream, Some(video)).await?
+                }
+                // Start of live or past stream is its actual start, not a reschedule
+                Some(video) if matches!(video.status, VideoStatus::Upcoming) => {
+                    let shift = video.available_at.naive_utc() - stream.scheduled_start;
+                    if shift.num_minutes().abs() >= self.reschedule_threshold_min {
+                        self.enqueue_reschedule_notice(&stream, video).await?;
+                    }
+                }
+                Some(_) => (),
+            }
+        }
+        Ok(())
+    }
+    // Notify users, that got reminders about the stream, that it was cancelled and forget it.
+    // Notices go through outbox, so popular stream doesn't hold the poll loop
+    async fn enqueue_cancel_notice(
+        &self,
+        stream: &ReportedStream,
+        video: Option<&holodex::model::Video>,
+    ) -> anyhow::Result<()> {
+        info!("Stream {} was cancelled", stream.video_id);
+        let users = queries::get_notified_users(self.get_pool(), &stream.video_id).await?;
+        let title = match video {
+            Some(video) => format!("\n\nНазвание: {}", mobot::api::escape_md(&video.title)),
+            None => String::new(),
+        };
+        let notices = users
+            .into_iter()
+            .map(|user| {
+                let text = format!(
+                    "❌ Отменено: {} {}, начало было запланировано на {}{}",
+                    video.map_or(VideoKind::Stream, VideoKind::of).label(),
+                    mobot::api::escape_md(&stream.channel_name),
+                    timezone::format_user_time(
+                        chrono::DateTime::from_naive_utc_and_offset(
+                            stream.scheduled_start,
+                            chrono::Utc
+                        ),
+                        &user.timezone
+                    ),
+                    title
+                );
+                (user, text)
+            })
+            .collect::<Vec<_>>();
+        queries::delete_reported_stream(
+            self.get_pool(),
+            &stream.video_id,
+            stream.scheduled_start,
+            &notices,
+        )
+        .await
+    }
+    // Notify users, that got reminders about the stream, about new start time and re-arm
+    // reminders. Notices go through outbox, so popular stream doesn't hold the poll loop
+    async fn enqueue_reschedule_notice(
+        &self,
+        stream: &ReportedStream,
+        video: &holodex::model::Video,
+    ) -> anyhow::Result<()> {
+        info!(
+            "Stream {} was rescheduled: {} -> {}",
+            stream.video_id,
+            stream.scheduled_start,
+            video.available_at.naive_utc()
+        );
+        let users = queries::get_notified_users(self.get_pool(), &stream.video_id).await?;
+        let notices = users
+            .into_iter()
+            .map(|user| {
+                let text = format!(
+                    "⏰ Перенос: {} {}, новое время начала {}\n\
+                    \n\
+                    Название: {}\n\
+                    \n\
+                    [▶️ Ссылка на видео](https://www.youtube.com/watch?v={})",
+                    VideoKind::of(video).label(),
+                    mobot::api::escape_md(&stream.channel_name),
+                    timezone::format_moved_time(
+                        video.available_at,
+                        chrono::DateTime::from_naive_utc_and_offset(
+                            stream.scheduled_start,
+                            chrono::Utc
+                        ),
+                        &user.timezone
+                    ),
+                    mobot::api::escape_md(&video.title),
+                    stream.video_id
+                );
+                (user, text)
+            })
+            .collect::<Vec<_>>();
+        queries::reschedule_reported_stream(self.get_pool(), video, &notices).await
+    }
     // Remove passed streams that users have been notified about
-    pub async fn clean_reported_streams(&self) {
+    pub async fn clean_reported_streams(&self) -> anyhow::Result<()> {
         let reported_streams = sqlx::query_as!(ReportedStream, "SELECT * FROM reported_stream")
             .fetch_all(&self.get_pool())
-            .await
-            .unwrap();
-        let time_now = chrono::Utc::now().naive_utc();
+            .await?;
+        // Keep streams, while they can still be fetched for 'started' reminder
+        let expired = chrono::Utc::now().naive_utc()
+            - chrono::Duration::hours(LATE_START_HOURS)
+            - chrono::Duration::minutes(LIVE_GRACE_MINUTES);
         for stream in reported_streams {
-            if stream.scheduled_start < time_now {
+            if stream.scheduled_start < expired {
                 sqlx::query!("DELETE FROM reported_stream WHERE id = ?", stream.id)
                     .execute(&self.get_pool())
-                    .await
-                    .unwrap();
+                    .await?;
             }
         }
+        sqlx::query!(
+            "DELETE FROM reported_notification WHERE scheduled_start < ?",
+            expired
+        )
+        .execute(&self.get_pool())
+        .await?;
+        // Outbox is kept for a while to see, what was delivered
+        let outbox_expired =
+            chrono::Utc::now().naive_utc() - chrono::Duration::days(outbox::KEEP_DAYS);
+        sqlx::query!(
+            "DELETE FROM outbox WHERE status != ? AND updated_at < ?",
+            outbox::STATUS_PENDING,
+            outbox_expired
+        )
+        .execute(&self.get_pool())
+        .await?;
+        Ok(())
     }
     // Associate fetched videos with vtubers. Drop videos, that don't belong to any vtuber in db
     pub async fn associate_video_vtuber(&self) -> anyhow::Result<Vec<VtuberVideo>> {
-        // Fetch vector of vtubers
-        let vtubers = sqlx::query_as!(crate::vtuber::Vtuber, "SELECT * FROM vtuber")
-            .fetch_all(&self.get_pool())
-            .await
-            .unwrap();
+        // Fetch vector of vtubers. Channels, that admin hasn't confirmed yet, and graduated
+        // vtubers aren't tracked
+        let vtubers = sqlx::query_as!(
+            Vtuber,
+            "SELECT * FROM vtuber WHERE is_confirmed AND status != 'graduated'"
+        )
+        .fetch_all(&self.get_pool())
+        .await?;
 
-        // Connect videos with vtubers. Filter out videos, that don't belong to any vtuber in db
+        // Connect videos with vtubers - channel owner and guests, mentioned in holodex or in
+        // the description. Filter out videos, that don't belong to any vtuber in db
         Ok(self
-            .get_videos()?
+            .get_videos(&vtubers)
+            .await?
             .into_iter()
             .filter_map(|video| {
-                match vtubers
+                let host = vtubers
                     .iter()
-                    .position(|vtuber| vtuber.youtube_channel_id == video.channel.id().to_string())
-                {
-                    Some(index) => Some(VtuberVideo {
-                        vtuber: vtubers[index].clone(),
+                    .find(|vtuber| vtuber.youtube_channel_id == video.channel.id().to_string())
+                    .cloned();
+                let partners = match &video.description {
+                    Some(description) => collab::find_partners(description, &vtubers),
+                    None => Vec::new(),
+                };
+                let guests = vtubers
+                    .iter()
+                    .filter(|vtuber| {
+                        video
+                            .mentions
+                            .iter()
+                            .any(|mention| vtuber.youtube_channel_id == mention.id.to_string())
+                            || partners.iter().any(|partner| partner.id == vtuber.id)
+                    })
+                    .filter(|vtuber| host.as_ref().map_or(true, |host| host.id != vtuber.id))
+                    .cloned()
+                    .collect::<Vec<_>>();
+                match host.is_none() && guests.is_empty() {
+                    true => None,
+                    false => Some(VtuberVideo {
+                        host,
+                        guests,
                         video,
                     }),
-                    None => None,
                 }
             })
             .collect())
@@ -138,66 +472,479 @@ impl MainClient {
             n if n >= 11 && n <= 19 => endings[2],
             n if n % 10 == 1 => endings[0],
             n if [2, 3, 4].contains(&(n % 10)) => endings[1],
-            _ => endings[2]
+            _ => endings[2],
+        }
+    }
+    // Build first line of the notification. User, that follows only guests of the stream,
+    // is told whose stream they appear in
+    fn build_header(&self, notification: &Notification) -> String {
+        let stream = notification.stream;
+        let time_left = notification.time_left;
+        let follows_host = stream.host.as_ref().map_or(false, |host| {
+            notification.followed.iter().any(|x| x.id == host.id)
+        });
+        let label = VideoKind::of(&stream.video).label();
+        let subject = match follows_host {
+            true => format!(
+                "{} {}",
+                label,
+                mobot::api::escape_md(&stream.channel_name())
+            ),
+            false => format!(
+                "{} появится в гостях у {}\\.",
+                mobot::api::escape_md(
+                    &notification
+                        .followed
+                        .iter()
+                        .map(|x| format!("{} {}", x.first_name, x.last_name))
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                ),
+                mobot::api::escape_md(&stream.channel_name())
+            ),
+        };
+        match (notification.stage, follows_host) {
+            (ReminderStage::Before(_), true) if time_left <= 0 => {
+                format!("{} вот\\-вот начнется", subject)
+            }
+            (ReminderStage::Before(_), true) => format!(
+                "{} начнется через \\~{} минут{}",
+                subject,
+                time_left,
+                self.pick_ending(time_left)
+            ),
+            (ReminderStage::Before(_), false) if time_left <= 0 => {
+                format!("{} {} вот\\-вот начнется", subject, label)
+            }
+            (ReminderStage::Before(_), false) => format!(
+                "{} {} начнется через \\~{} минут{}",
+                subject,
+                label,
+                time_left,
+                self.pick_ending(time_left)
+            ),
+            (ReminderStage::Started, true) => format!("🔴 {} уже в эфире\\!", subject),
+            (ReminderStage::Started, false) => format!("🔴 {} {} уже в эфире\\!", subject, label),
+        }
+    }
+    // Get start of the stream to show. Live stream shows its actual start
+    fn get_start(&self, notification: &Notification) -> chrono::DateTime<chrono::Utc> {
+        let video = &notification.stream.video;
+        match notification.stage {
+            ReminderStage::Started => video.live_info.start_actual.unwrap_or(video.available_at),
+            ReminderStage::Before(_) => video.available_at,
         }
     }
-    // Notify all subscribed users about the stream
-    pub async fn send_notification(&self, stream: VtuberVideo) {
-        // Get all users, that subscribed to this vtuber
-        let users = sqlx::query_as!(
-            crate::user::User,
-            r#"SELECT user.* FROM user JOIN user_vtuber ON user_vtuber.user_id = user.tg_user_id WHERE user_vtuber.vtuber_id = ?"#,
-            stream.vtuber.id
+    // Build notification text for the reminder stage
+    fn build_caption(&self, user: &User, notification: &Notification) -> String {
+        let stream = notification.stream;
+        // Collab partners are listed for everyone
+        let collab = match stream.guests.is_empty() {
+            true => String::new(),
+            false => format!(
+                "\nКоллаб: {}",
+                mobot::api::escape_md(
+                    &stream
+                        .participants()
+                        .iter()
+                        .map(|x| format!("{} {}", x.first_name, x.last_name))
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                )
+            ),
+        };
+        let topic = match &stream.video.topic {
+            Some(topic) => format!("\nТема: {}", mobot::api::escape_md(topic)),
+            None => String::new(),
+        };
+        format!(
+            "{}\n\
+            \n\
+            Название: {}{}{}\n\
+            \n\
+            [▶️ Ссылка на видео](https://www.youtube.com/watch?v={})\n\
+            Начало: {}",
+            self.build_header(notification),
+            mobot::api::escape_md(&stream.video.title),
+            topic,
+            collab,
+            stream.video.id.to_string(),
+            timezone::format_user_time(self.get_start(notification), &user.timezone)
         )
-        .fetch_all(&self.get_pool())
-        .await
-        .unwrap();
-        // Notify every user
-        for user in &users {
-            // Get GMT+3 datetime
-            let local_date_gmt3 =
-                stream.video.available_at.naive_utc() + chrono::Duration::hours(3);
-            let time_left = (stream.video.available_at.naive_utc() - chrono::Utc::now().naive_utc()).num_minutes();
-            // Send thumbnail and text-message
-            let res = self
-                .tg_api
-                .send_photo(
-                    &SendPhotoRequest::new_external_url(
-                        user.tg_chat_id,
-                        format!(
-                            "https://img.youtube.com/vi/{}/0.jpg",
-                            stream.video.id.to_string()
-                        ),
-                    )
-                    .with_caption(format!(
-                        "Стрим {} {} начнется через \\~{} минут{}\n\
-                        \n\
-                        Название: {}\n\
-                        \n\
-                        [▶️ Ссылка на стрим](https://www.youtube.com/watch?v={})\n\
-                        Начало: {:02}:{:02} \\(GMT\\+3 Europe/Moscow\\)",
-                        stream.vtuber.first_name,
-                        stream.vtuber.last_name,
+    }
+    // Build single message about several streams
+    fn build_digest(&self, user: &User, notifications: &[Notification]) -> String {
+        let lines = notifications
+            .iter()
+            .map(|x| {
+                format!(
+                    "{}\n[▶️ {}](https://www.youtube.com/watch?v={})\n\
+                    Начало: {}",
+                    self.build_header(x),
+                    mobot::api::escape_md(&x.stream.video.title),
+                    x.stream.video.id.to_string(),
+                    timezone::format_user_time(self.get_start(x), &user.timezone)
+                )
+            })
+            .collect::<Vec<_>>();
+        format!("📋 Ближайшие стримы\n\n{}", lines.join("\n\n"))
+    }
+    // Get users, subscribed to host or guests of the stream, with vtubers they follow in it.
+    // Every user is listed once, so nobody gets two messages about the same video
+    async fn get_stream_subscribers(
+        &self,
+        stream: &VtuberVideo,
+    ) -> anyhow::Result<Vec<(User, Vec<Vtuber>)>> {
+        let mut users: Vec<(User, Vec<Vtuber>)> = Vec::new();
+        for vtuber in stream.participants() {
+            for user in queries::get_subscribers(self.get_pool(), vtuber.id).await? {
+                match users.iter_mut().find(|x| x.0.tg_user_id == user.tg_user_id) {
+                    Some(entry) => entry.1.push(vtuber.clone()),
+                    None => users.push((user, vec![vtuber.clone()])),
+                }
+            }
+        }
+        Ok(users)
+    }
+    // Get notifications about the stream for subscribed users, whose reminder stage is due
+    async fn collect_notifications<'a>(
+        &self,
+        stream: &'a VtuberVideo,
+    ) -> anyhow::Result<Vec<(User, Notification<'a>)>> {
+        // Get all users, that subscribed to host or guests of the stream
+        let users = self.get_stream_subscribers(stream).await?;
+        let video_id = stream.video.id.to_string();
+        let time_left =
+            (stream.video.available_at.naive_utc() - chrono::Utc::now().naive_utc()).num_minutes();
+        let kind = VideoKind::of(&stream.video);
+        let mut notifications = Vec::new();
+        for (user, followed) in users {
+            // User can opt out of premieres, placeholders, etc.
+            if !queries::get_user_video_kinds(self.get_pool(), user.tg_user_id)
+                .await?
+                .contains(&kind)
+            {
+                continue;
+            }
+            // Topic rules of the user
+            let topics = queries::get_user_topics(self.get_pool(), user.tg_user_id).await?;
+            if !topic::is_allowed(
+                stream.video.topic.as_deref(),
+                user.get_topic_mode(),
+                &topics,
+            ) {
+                continue;
+            }
+            let lead_times = queries::get_user_lead_times(self.get_pool(), user.tg_user_id).await?;
+            let reported =
+                queries::get_reported_stages(self.get_pool(), user.tg_user_id, &video_id).await?;
+            let stages = reminder::user_stages(&lead_times, user.started_ping);
+            // Several stages can become due at once (e.g. stream was announced late),
+            // so user gets only one message for all of them
+            let is_live = matches!(stream.video.status, VideoStatus::Live);
+            let due = reminder::due_stages(time_left, is_live, &stages, &reported);
+            if let Some(stage) = reminder::latest_stage(&due) {
+                notifications.push((
+                    user,
+                    Notification {
+                        stream,
+                        followed,
+                        stage,
+                        due,
                         time_left,
-                        self.pick_ending(time_left),
-                        mobot::api::escape_md(&stream.video.title),
+                    },
+                ));
+            }
+        }
+        // Remember the stream to clean up notifications after its start
+        if !notifications.is_empty()
+            && queries::is_stream_reported(self.get_pool(), &stream.video)
+                .await?
+                .is_none()
+        {
+            queries::insert_reported_stream(
+                self.get_pool(),
+                &stream.video,
+                stream.main_vtuber(),
+                &stream.channel_name(),
+            )
+            .await?;
+        }
+        Ok(notifications)
+    }
+    // Get items of the outbox message: every due stage of every notification
+    fn outbox_items(&self, notifications: &[Notification]) -> Vec<OutboxItem> {
+        notifications
+            .iter()
+            .flat_map(|x| {
+                // Stage of the message goes first, so it becomes the parent entry
+                std::iter::once(x.stage)
+                    .chain(x.due.iter().cloned().filter(|stage| *stage != x.stage))
+                    .map(|stage| OutboxItem {
+                        video_id: x.stream.video.id.to_string(),
+                        scheduled_start: x.stream.video.available_at.naive_utc(),
+                        stage,
+                    })
+            })
+            .collect()
+    }
+    // Put notifications about streams, whose reminder stages are due, into the outbox.
+    // Users in digest mode get one message for all streams of the poll cycle. Stream, that
+    // can't be processed (e.g. db is busy), is skipped until the next poll
+    pub async fn enqueue_notifications(&self, streams: &[VtuberVideo]) {
+        // Group notifications by user, keeping order of streams
+        let mut users: Vec<(User, Vec<Notification>)> = Vec::new();
+        for stream in streams {
+            let notifications = match self.collect_notifications(stream).await {
+                Ok(notifications) => notifications,
+                Err(e) => {
+                    error!(
+                        "Error during collecting notifications for {}: {}",
                         stream.video.id.to_string(),
-                        local_date_gmt3.hour(),
-                        local_date_gmt3.minute()
-                    ))
+                        e
+                    );
+                    self.send_alert(e).await;
+                    continue;
+                }
+            };
+            for (user, notification) in notifications {
+                match users.iter_mut().find(|x| x.0.tg_user_id == user.tg_user_id) {
+                    Some(entry) => entry.1.push(notification),
+                    None => users.push((user, vec![notification])),
+                }
+            }
+        }
+        for (user, notifications) in &users {
+            let messages = match user.digest_mode && notifications.len() > 1 {
+                true => vec![OutboxMessage {
+                    photo_url: None,
+                    text: self.build_digest(user, notifications),
+                    items: self.outbox_items(notifications),
+                }],
+                // Thumbnail with text
+                false => notifications
+                    .iter()
+                    .map(|notification| OutboxMessage {
+                        photo_url: Some(format!(
+                            "https://img.youtube.com/vi/{}/0.jpg",
+                            notification.stream.video.id.to_string()
+                        )),
+                        text: self.build_caption(user, notification),
+                        items: self.outbox_items(std::slice::from_ref(notification)),
+                    })
+                    .collect(),
+            };
+            for message in messages {
+                if let Err(e) =
+                    queries::enqueue_outbox_message(self.get_pool(), user, &message).await
+                {
+                    error!("Enqueue failed for UserID<{}>: {}", user.tg_user_id, e);
+                }
+            }
+        }
+    }
+    // Send pending outbox messages. Users are served concurrently (up to the parallelism
+    // limit), messages of one user are sent one by one in order of creation. Failed messages
+    // are retried after delay, until attempts are exhausted. Full batch means, there can be
+    // more messages, so the next one is taken right away: pace is set by the send queue
+    pub async fn drain_outbox(self: Arc<Self>) -> anyhow::Result<()> {
+        loop {
+            let entries = queries::get_pending_outbox(self.get_pool(), outbox::BATCH_SIZE).await?;
+            let is_full = entries.len() as i64 >= outbox::BATCH_SIZE;
+            // Group entries by user, keeping order
+            let mut users: Vec<(i64, Vec<OutboxEntry>)> = Vec::new();
+            for entry in entries {
+                match users.iter_mut().find(|x| x.0 == entry.user_id) {
+                    Some(user) => user.1.push(entry),
+                    None => users.push((entry.user_id, vec![entry])),
+                }
+            }
+            self.fan_out(
+                "delivering outbox",
+                users.into_iter().map(|x| x.1).collect(),
+                |main_client, entries| async move {
+                    main_client.deliver_user_outbox(entries).await
+                },
+            )
+            .await?;
+            if !is_full {
+                return Ok(());
+            }
+        }
+    }
+    // Run task for every item concurrently, up to the parallelism limit. Failed and panicked
+    // tasks are logged, so one user doesn't stop the others
+    pub async fn fan_out<T, F, Fut>(
+        self: &Arc<Self>,
+        action: &str,
+        items: Vec<T>,
+        task: F,
+    ) -> anyhow::Result<()>
+    where
+        F: Fn(Arc<Self>, T) -> Fut,
+        Fut: Future<Output = anyhow::Result<()>> + Send + 'static,
+    {
+        let mut tasks = JoinSet::new();
+        for item in items {
+            let permit = self.send_limit.clone().acquire_owned().await?;
+            let res = task(self.clone(), item);
+            tasks.spawn(async move {
+                let res = res.await;
+                drop(permit);
+                res
+            });
+        }
+        while let Some(res) = tasks.join_next().await {
+            match res {
+                Ok(Err(e)) => error!("Error during {}: {}", action, e),
+                Err(e) => error!("Task panicked during {}: {}", action, e),
+                Ok(Ok(_)) => (),
+            }
+        }
+        Ok(())
+    }
+    // Send outbox messages of one user in order. After failure the rest waits for the retry,
+    // so messages never overtake each other
+    async fn deliver_user_outbox(&self, entries: Vec<OutboxEntry>) -> anyhow::Result<()> {
+        for entry in entries {
+            if !self.deliver_outbox_entry(&entry).await? {
+                break;
+            }
+        }
+        Ok(())
+    }
+    // Send outbox message and save the result. Returns true, if message was delivered
+    async fn deliver_outbox_entry(&self, entry: &OutboxEntry) -> anyhow::Result<bool> {
+        let message = match &entry.photo_url {
+            Some(url) => OutboundMessage::Photo(
+                SendPhotoRequest::new_external_url(entry.chat_id, url.clone())
+                    .with_caption(entry.text.clone())
                     .with_parse_mode(ParseMode::MarkdownV2),
+            ),
+            None => OutboundMessage::Text(
+                SendMessageRequest::new(entry.chat_id, entry.text.clone())
+                    .with_parse_mode(ParseMode::MarkdownV2),
+            ),
+        };
+        let time_now = chrono::Utc::now().naive_utc();
+        let attempts = entry.attempts + 1;
+        let (status, last_error, next_attempt_at) = match self.send_queue.send(message).await {
+            Ok(_) => (outbox::STATUS_SENT, None, time_now),
+            Err(e) => {
+                warn!("Attempt {} of outbox entry {}", attempts, entry.id);
+                // Message to blocked user will never be delivered
+                let blocked = self
+                    .handle_send_error(entry.user_id, "User-notify", &e)
+                    .await;
+                let status = match blocked || attempts >= outbox::MAX_ATTEMPTS {
+                    true => outbox::STATUS_FAILED,
+                    false => outbox::STATUS_PENDING,
+                };
+                (
+                    status,
+                    Some(e.to_string()),
+                    time_now + chrono::Duration::seconds(outbox::RETRY_DELAY_SEC * attempts),
                 )
-                .await;
-            debug!("User-notify: {:?}", res);
+            }
+        };
+        queries::update_outbox_status(
+            self.get_pool(),
+            entry.id,
+            status,
+            attempts,
+            last_error,
+            next_attempt_at,
+        )
+        .await?;
+        Ok(status == outbox::STATUS_SENT)
+    }
+    // Tell subscribers of graduated vtubers and vtubers on hiatus about it. Every status is
+    // announced once, message offers to unsubscribe. Subscribers are notified concurrently
+    pub async fn send_status_notices(self: Arc<Self>) -> anyhow::Result<()> {
+        let mut notices = Vec::new();
+        for vtuber in queries::get_inactive_vtubers(self.get_pool()).await? {
+            let notice = match vtuber.get_status().notice() {
+                Some(notice) => notice,
+                None => continue,
+            };
+            for user in queries::get_subscribers(self.get_pool(), vtuber.id).await? {
+                notices.push((vtuber.clone(), notice, user));
+            }
         }
-        if !users.is_empty() {
-            crate::queries::insert_reported_stream(self.get_pool(), &stream.video, &stream.vtuber)
+        self.fan_out(
+            "sending status notice",
+            notices,
+            |main_client, (vtuber, notice, user)| async move {
+                main_client.send_status_notice(&vtuber, notice, &user).await
+            },
+        )
+        .await
+    }
+    // Send status notice to user, unless it was already sent. Notice is claimed before sending,
+    // so it isn't sent twice. If telegram failed temporarily, claim is released and notice is
+    // retried on the next check
+    async fn send_status_notice(
+        &self,
+        vtuber: &Vtuber,
+        notice: &str,
+        user: &User,
+    ) -> anyhow::Result<()> {
+        if !queries::claim_status_notice(self.get_pool(), user.tg_user_id, vtuber).await? {
+            return Ok(());
+        }
+        let res = self
+            .send_queue
+            .send(OutboundMessage::Text(
+                SendMessageRequest::new(
+                    user.tg_chat_id,
+                    format!(
+                        "{} {} {} {} {}",
+                        vtuber.get_status().badge(),
+                        vtuber.first_name,
+                        vtuber.last_name,
+                        vtuber.emoji,
+                        notice
+                    ),
+                )
+                .with_reply_markup(api::ReplyMarkup::inline_keyboard_markup(vec![
+                    vec![api::InlineKeyboardButton::from("🧹 Убрать из моего списка")
+                        .with_callback_data(format!("unsub_{}", vtuber.id))],
+                ])),
+            ))
+            .await;
+        if let Err(e) = res {
+            if !self
+                .handle_send_error(user.tg_user_id, "User-status-notify", &e)
                 .await
-                .unwrap();
+                && send_queue::is_temporary(&e.to_string())
+            {
+                queries::release_status_notice(self.get_pool(), user.tg_user_id, vtuber).await?;
+            }
         }
+        Ok(())
     }
+    // Log failed send. Users, who blocked the bot or deleted their account, are deactivated.
+    // Returns true, if user was deactivated
+    pub async fn handle_send_error(
+        &self,
+        tg_user_id: i64,
+        action: &str,
+        e: &anyhow::Error,
+    ) -> bool {
+        warn!("{} failed for UserID<{}>: {}", action, tg_user_id, e);
+        if !send_queue::is_blocked(&e.to_string()) {
+            return false;
+        }
+        info!("UserID<{}> is unreachable, deactivating", tg_user_id);
+        if let Err(e) = queries::deactivate_user(self.get_pool(), tg_user_id).await {
+            error!("Deactivation failed for UserID<{}>: {}", tg_user_id, e);
+        }
+        true
+    }
+    // Notify admins through monitoring service. Unavailable monitoring is only logged, so
+    // polling goes on
     pub async fn send_alert(&self, e: anyhow::Error) {
-        let req = self
+        let res = self
             .alert_client
             .post(self.monitoring_ip.clone() + "/notify/fire")
             .json(&AlertRequest {
@@ -205,8 +952,10 @@ impl MainClient {
                 theme: "fire".to_owned(),
                 text: format!("{}", e),
             })
-            .build()
-            .unwrap();
-        self.alert_client.execute(req).await.unwrap();
+            .send()
+            .await;
+        if let Err(alert_error) = res {
+            error!("Alert failed: {}. Original error: {}", alert_error, e);
+        }
     }
 }
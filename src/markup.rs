@@ -1,31 +1,86 @@
 use mobot::*;
 use sqlx::{Pool, Sqlite};
 
+use crate::digest::DIGEST_TIME_OPTIONS;
 use crate::queries;
+use crate::reminder::LEAD_TIME_OPTIONS;
+use crate::timezone::TIMEZONE_OPTIONS;
+use crate::topic::TOPIC_OPTIONS;
+use crate::video_kind::VideoKind;
+use crate::vtuber::Vtuber;
 
-// Markup for selecting a wave
-pub async fn waves_markup(pool: Pool<Sqlite>, tg_user_id: i64) -> api::ReplyMarkup {
-    let waves = queries::get_amount_in_waves(pool, tg_user_id).await;
-    match waves {
-        Ok(waves) => api::ReplyMarkup::inline_keyboard_markup(
-            waves
-                .into_iter()
-                .map(|x| {
-                    vec![api::InlineKeyboardButton::from(format!(
-                        "{} ({}/{})",
-                        x.wave_name.clone(),
-                        x.amount,
-                        x.max_amount
-                    ))
-                    .with_callback_data(format!("wave_{}", x.wave_name))]
-                })
-                .collect(),
+// Markup, shown instead of vtuber catalog, if it can't be loaded
+fn catalog_error_markup() -> api::ReplyMarkup {
+    api::ReplyMarkup::inline_keyboard_markup(vec![vec![api::InlineKeyboardButton::from(
+        "Извините, возникла ошибка. Попробуйте позже отправить команду /waves или /start",
+    )]])
+}
+
+// Markup for groups of vtubers with amount of selected members. Every button gets callback
+// data from the id of vtuber in the group, 'back' button is added, if it is set
+fn groups_markup(
+    groups: Vec<queries::GroupAmount>,
+    callback_data: impl Fn(i64) -> String,
+    back: Option<String>,
+) -> api::ReplyMarkup {
+    let mut buttons = groups
+        .into_iter()
+        .map(|x| {
+            vec![api::InlineKeyboardButton::from(format!(
+                "{} ({}/{})",
+                x.name.clone(),
+                x.amount,
+                x.max_amount
+            ))
+            .with_callback_data(callback_data(x.vtuber_id))]
+        })
+        .collect::<Vec<_>>();
+    if let Some(back) = back {
+        buttons.push(vec![
+            api::InlineKeyboardButton::from("Назад").with_callback_data(back)
+        ]);
+    }
+    api::ReplyMarkup::inline_keyboard_markup(buttons)
+}
+
+// Markup for selecting an organization
+pub async fn organizations_markup(pool: Pool<Sqlite>, tg_user_id: i64) -> api::ReplyMarkup {
+    match queries::get_amount_in_organizations(pool, tg_user_id).await {
+        Ok(organizations) => groups_markup(organizations, |x| format!("org_{}", x), None),
+        Err(_) => catalog_error_markup(),
+    }
+}
+
+// Markup for selecting a branch of the organization of the vtuber
+pub async fn branches_markup(
+    pool: Pool<Sqlite>,
+    tg_user_id: i64,
+    vtuber: &Vtuber,
+) -> api::ReplyMarkup {
+    match queries::get_amount_in_branches(pool, tg_user_id, &vtuber.organization).await {
+        Ok(branches) => groups_markup(
+            branches,
+            |x| format!("branch_{}", x),
+            Some(String::from("org_back")),
         ),
-        Err(_) => {
-            api::ReplyMarkup::inline_keyboard_markup(vec![vec![api::InlineKeyboardButton::from(
-                "Извините, возникла ошибка. Попробуйте позже отправить команду /waves или /start",
-            )]])
-        }
+        Err(_) => catalog_error_markup(),
+    }
+}
+
+// Markup for selecting a wave of the branch of the vtuber
+pub async fn waves_markup(
+    pool: Pool<Sqlite>,
+    tg_user_id: i64,
+    vtuber: &Vtuber,
+) -> api::ReplyMarkup {
+    match queries::get_amount_in_waves(pool, tg_user_id, &vtuber.organization, &vtuber.branch).await
+    {
+        Ok(waves) => groups_markup(
+            waves,
+            |x| format!("wave_{}", x),
+            Some(format!("org_{}", vtuber.id)),
+        ),
+        Err(_) => catalog_error_markup(),
     }
 }
 
@@ -38,38 +93,231 @@ fn get_member_status_badge(is_selected: bool) -> &'static str {
     }
 }
 
-// Markup for selecting members
+// Markup for selecting members of the wave of the vtuber
 pub async fn members_markup(
     pool: Pool<Sqlite>,
     tg_user_id: i64,
-    wave_name: String,
+    vtuber: &Vtuber,
 ) -> api::ReplyMarkup {
-    let members = queries::get_wave_members(pool, tg_user_id, wave_name).await;
+    let members = queries::get_wave_members(
+        pool,
+        tg_user_id,
+        &vtuber.organization,
+        &vtuber.branch,
+        &vtuber.wave_name,
+    )
+    .await;
     match members {
         Ok(members) => {
             let mut members = members
                 .into_iter()
                 .map(|x| {
                     vec![api::InlineKeyboardButton::from(format!(
-                        "{}{} {} {}",
+                        "{}{}{} {} {}",
                         get_member_status_badge(x.is_selected),
+                        x.vtuber.get_status().badge(),
                         x.vtuber.first_name,
                         x.vtuber.last_name,
                         x.vtuber.emoji
                     ))
-                    .with_callback_data(format!(
-                        "member_{} {} wave_{}",
-                        x.vtuber.first_name, x.vtuber.last_name, x.vtuber.wave_name
-                    ))]
+                    .with_callback_data(format!("member_{}", x.vtuber.id))]
                 })
                 .collect::<Vec<_>>();
             members.push(vec![api::InlineKeyboardButton::from("Назад")
-                .with_callback_data("member_back wave_none")]);
+                .with_callback_data(format!("branch_{}", vtuber.id))]);
             api::ReplyMarkup::inline_keyboard_markup(members)
         }
+        Err(_) => catalog_error_markup(),
+    }
+}
+
+// Markup for settings menu
+pub async fn settings_markup(pool: Pool<Sqlite>, tg_user_id: i64) -> api::ReplyMarkup {
+    let user = queries::get_user(pool, tg_user_id).await;
+    match user {
+        Ok(user) => api::ReplyMarkup::inline_keyboard_markup(vec![
+            vec![api::InlineKeyboardButton::from("⏰ Время напоминаний")
+                .with_callback_data("settings_lead")],
+            vec![api::InlineKeyboardButton::from("🌍 Часовой пояс")
+                .with_callback_data("settings_tz")],
+            vec![api::InlineKeyboardButton::from("🎬 Типы видео")
+                .with_callback_data("settings_kind")],
+            vec![api::InlineKeyboardButton::from("🏷 Темы").with_callback_data("settings_topic")],
+            vec![api::InlineKeyboardButton::from(format!(
+                "{}📋 Одно сообщение о нескольких стримах",
+                get_member_status_badge(user.digest_mode)
+            ))
+            .with_callback_data("settings_digest")],
+            vec![api::InlineKeyboardButton::from("🌅 Расписание на день и неделю")
+                .with_callback_data("settings_schedule")],
+        ]),
+        Err(_) => {
+            api::ReplyMarkup::inline_keyboard_markup(vec![vec![api::InlineKeyboardButton::from(
+                "Извините, возникла ошибка. Попробуйте позже отправить команду /settings или /start",
+            )]])
+        }
+    }
+}
+
+// Markup for selecting reminder schedule: lead times and reminder at the start
+pub async fn lead_times_markup(pool: Pool<Sqlite>, tg_user_id: i64) -> api::ReplyMarkup {
+    let lead_times = queries::get_user_lead_times(pool.clone(), tg_user_id).await;
+    let user = queries::get_user(pool, tg_user_id).await;
+    match (lead_times, user) {
+        (Ok(lead_times), Ok(user)) => {
+            let mut buttons = LEAD_TIME_OPTIONS
+                .iter()
+                .map(|x| {
+                    vec![api::InlineKeyboardButton::from(format!(
+                        "{}За {} минут",
+                        get_member_status_badge(lead_times.contains(x)),
+                        x
+                    ))
+                    .with_callback_data(format!("lead_{}", x))]
+                })
+                .collect::<Vec<_>>();
+            buttons.push(vec![api::InlineKeyboardButton::from(format!(
+                "{}🔴 Когда стрим начнется",
+                get_member_status_badge(user.started_ping)
+            ))
+            .with_callback_data("lead_started")]);
+            buttons.push(vec![
+                api::InlineKeyboardButton::from("Назад").with_callback_data("settings_back")
+            ]);
+            api::ReplyMarkup::inline_keyboard_markup(buttons)
+        }
+        _ => api::ReplyMarkup::inline_keyboard_markup(vec![vec![api::InlineKeyboardButton::from(
+            "Извините, возникла ошибка. Попробуйте позже отправить команду /settings",
+        )]]),
+    }
+}
+
+// Markup for selecting timezone
+pub async fn timezone_markup(pool: Pool<Sqlite>, tg_user_id: i64) -> api::ReplyMarkup {
+    let user = queries::get_user(pool, tg_user_id).await;
+    match user {
+        Ok(user) => {
+            let mut buttons = TIMEZONE_OPTIONS
+                .iter()
+                .map(|x| {
+                    vec![api::InlineKeyboardButton::from(format!(
+                        "{}{}",
+                        get_member_status_badge(user.timezone == *x),
+                        x
+                    ))
+                    .with_callback_data(format!("tz_{}", x))]
+                })
+                .collect::<Vec<_>>();
+            buttons.push(vec![
+                api::InlineKeyboardButton::from("Назад").with_callback_data("settings_back")
+            ]);
+            api::ReplyMarkup::inline_keyboard_markup(buttons)
+        }
+        Err(_) => {
+            api::ReplyMarkup::inline_keyboard_markup(vec![vec![api::InlineKeyboardButton::from(
+                "Извините, возникла ошибка. Попробуйте позже отправить команду /settings",
+            )]])
+        }
+    }
+}
+
+// Markup for selecting kinds of videos among kinds, that bot fetches
+pub async fn video_kinds_markup(
+    pool: Pool<Sqlite>,
+    tg_user_id: i64,
+    available: Vec<VideoKind>,
+) -> api::ReplyMarkup {
+    let kinds = queries::get_user_video_kinds(pool, tg_user_id).await;
+    match kinds {
+        Ok(kinds) => {
+            let mut buttons = available
+                .iter()
+                .map(|x| {
+                    vec![api::InlineKeyboardButton::from(format!(
+                        "{}{}",
+                        get_member_status_badge(kinds.contains(x)),
+                        x.label()
+                    ))
+                    .with_callback_data(format!("kind_{}", x))]
+                })
+                .collect::<Vec<_>>();
+            buttons.push(vec![
+                api::InlineKeyboardButton::from("Назад").with_callback_data("settings_back")
+            ]);
+            api::ReplyMarkup::inline_keyboard_markup(buttons)
+        }
+        Err(_) => {
+            api::ReplyMarkup::inline_keyboard_markup(vec![vec![api::InlineKeyboardButton::from(
+                "Извините, возникла ошибка. Попробуйте позже отправить команду /settings",
+            )]])
+        }
+    }
+}
+
+// Markup for selecting topics and the way they are applied
+pub async fn topics_markup(pool: Pool<Sqlite>, tg_user_id: i64) -> api::ReplyMarkup {
+    let topics = queries::get_user_topics(pool.clone(), tg_user_id).await;
+    let user = queries::get_user(pool, tg_user_id).await;
+    match (topics, user) {
+        (Ok(topics), Ok(user)) => {
+            let mut buttons = vec![vec![api::InlineKeyboardButton::from(format!(
+                "Режим: {}",
+                user.get_topic_mode().label()
+            ))
+            .with_callback_data("topic_mode")]];
+            buttons.extend(TOPIC_OPTIONS.iter().map(|x| {
+                vec![api::InlineKeyboardButton::from(format!(
+                    "{}{}",
+                    get_member_status_badge(topics.iter().any(|y| y == x)),
+                    x
+                ))
+                .with_callback_data(format!("topic_{}", x))]
+            }));
+            buttons.push(vec![
+                api::InlineKeyboardButton::from("Назад").with_callback_data("settings_back")
+            ]);
+            api::ReplyMarkup::inline_keyboard_markup(buttons)
+        }
+        _ => api::ReplyMarkup::inline_keyboard_markup(vec![vec![api::InlineKeyboardButton::from(
+            "Извините, возникла ошибка. Попробуйте позже отправить команду /settings",
+        )]]),
+    }
+}
+
+// Markup for selecting time of daily digest and weekly digest
+pub async fn digest_markup(pool: Pool<Sqlite>, tg_user_id: i64) -> api::ReplyMarkup {
+    let user = queries::get_user(pool, tg_user_id).await;
+    match user {
+        Ok(user) => {
+            let mut buttons = DIGEST_TIME_OPTIONS
+                .iter()
+                .map(|x| {
+                    vec![api::InlineKeyboardButton::from(format!(
+                        "{}Каждый день в {}",
+                        get_member_status_badge(user.digest_time.as_deref() == Some(*x)),
+                        x
+                    ))
+                    .with_callback_data(format!("digest_time_{}", x))]
+                })
+                .collect::<Vec<_>>();
+            buttons.push(vec![api::InlineKeyboardButton::from(format!(
+                "{}Без ежедневного расписания",
+                get_member_status_badge(user.digest_time.is_none())
+            ))
+            .with_callback_data("digest_time_off")]);
+            buttons.push(vec![api::InlineKeyboardButton::from(format!(
+                "{}Обзор недели по воскресеньям",
+                get_member_status_badge(user.weekly_digest)
+            ))
+            .with_callback_data("digest_weekly")]);
+            buttons.push(vec![
+                api::InlineKeyboardButton::from("Назад").with_callback_data("settings_back")
+            ]);
+            api::ReplyMarkup::inline_keyboard_markup(buttons)
+        }
         Err(_) => {
             api::ReplyMarkup::inline_keyboard_markup(vec![vec![api::InlineKeyboardButton::from(
-                "Извините, возникла ошибка. Попробуйте позже отправить команду /waves или /start",
+                "Извините, возникла ошибка. Попробуйте позже отправить команду /settings",
             )]])
         }
     }
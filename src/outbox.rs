@@ -0,0 +1,68 @@
+use chrono;
+use serde;
+use serde::Deserialize;
+
+use crate::reminder::ReminderStage;
+
+// Delivery statuses of outbox entries
+pub const STATUS_PENDING: &str = "pending";
+pub const STATUS_SENT: &str = "sent";
+pub const STATUS_FAILED: &str = "failed";
+
+// Delivery attempts before entry is marked as failed
+pub const MAX_ATTEMPTS: i64 = 3;
+
+// Delay before retry, multiplied by number of attempts
+pub const RETRY_DELAY_SEC: i64 = 60;
+
+// Messages, taken from the outbox at once. Drain takes batches, until the outbox is empty
+pub const BATCH_SIZE: i64 = 100;
+
+// How long delivered and failed entries are kept for audit
+pub const KEEP_DAYS: i64 = 30;
+
+// Stages of notices about the video itself. They go through outbox after the reminders,
+// but aren't reminder stages
+pub const NOTICE_CANCELLED: &str = "cancelled";
+pub const NOTICE_RESCHEDULED: &str = "rescheduled";
+
+// Outbox entry: reminder stage of the video for user and its delivery status
+#[derive(Deserialize, Clone, Debug, Default)]
+pub struct OutboxEntry {
+    #[serde(skip)]
+    pub id: i64,
+    pub user_id: i64,
+    pub chat_id: i64,
+    // Youtube video id
+    pub video_id: String,
+    pub stage: String,
+    pub scheduled_start: chrono::NaiveDateTime,
+    // Entry, whose message also delivers this one
+    pub parent_id: Option<i64>,
+    // Thumbnail for photo message, None for text message
+    pub photo_url: Option<String>,
+    // MarkdownV2 text or caption
+    pub text: String,
+    pub status: String,
+    pub attempts: i64,
+    pub last_error: Option<String>,
+    pub next_attempt_at: chrono::NaiveDateTime,
+    pub created_at: chrono::NaiveDateTime,
+    pub updated_at: chrono::NaiveDateTime,
+}
+
+// Reminder stage of the video, that is delivered with outbox message
+#[derive(Clone, Debug)]
+pub struct OutboxItem {
+    pub video_id: String,
+    pub scheduled_start: chrono::NaiveDateTime,
+    pub stage: ReminderStage,
+}
+
+// Message for user, that delivers one or several reminder stages. First item is the parent
+#[derive(Clone, Debug)]
+pub struct OutboxMessage {
+    pub photo_url: Option<String>,
+    pub text: String,
+    pub items: Vec<OutboxItem>,
+}
@@ -1,14 +1,17 @@
 use anyhow::anyhow;
-use lazy_static::lazy_static;
 use sqlx::error;
 use sqlx::sqlite::SqliteRow;
 use sqlx::Pool;
 use sqlx::Row;
 use sqlx::Sqlite;
-use std::collections::HashMap;
 
+use crate::outbox::{self, OutboxEntry, OutboxMessage};
+use crate::reminder::ReminderStage;
+use crate::topic::TopicMode;
+use crate::video_kind::VideoKind;
 use crate::vtuber;
 use crate::vtuber::Vtuber;
+use crate::vtuber_status::VtuberStatus;
 
 // Insers user into db
 pub async fn insert_user(
@@ -16,7 +19,7 @@ pub async fn insert_user(
     user: &mobot::api::User,
     chat_id: i64,
 ) -> Result<(), anyhow::Error> {
-    match sqlx::query!(
+    sqlx::query!(
         r#"INSERT INTO user (first_name, last_name, username, tg_user_id, tg_chat_id)
         VALUES (?, ?, ?, ?, ?)"#,
         user.first_name,
@@ -26,6 +29,22 @@ pub async fn insert_user(
         chat_id
     )
     .execute(&pool)
+    .await?;
+    // Every new user starts with default lead time and streams only
+    sqlx::query!(
+        r#"INSERT INTO user_lead_time (user_id, minutes) VALUES (?, ?)"#,
+        user.id,
+        crate::reminder::DEFAULT_LEAD_TIME
+    )
+    .execute(&pool)
+    .await?;
+    let kind = VideoKind::Stream.to_string();
+    match sqlx::query!(
+        r#"INSERT INTO user_video_kind (user_id, kind) VALUES (?, ?)"#,
+        user.id,
+        kind
+    )
+    .execute(&pool)
     .await
     {
         Ok(_) => Ok(()),
@@ -33,25 +52,53 @@ pub async fn insert_user(
     }
 }
 
-// Is vtuber in database
-pub async fn is_vtuber_exist(
+// Reactivate returning user and refresh their data. Returns false, if user isn't in db yet
+pub async fn reactivate_user(
     pool: Pool<Sqlite>,
-    member: &vtuber::Vtuber,
+    user: &mobot::api::User,
+    chat_id: i64,
 ) -> Result<bool, anyhow::Error> {
     match sqlx::query!(
-        r#"SELECT first_name FROM vtuber
-        WHERE first_name = ? AND last_name = ?"#,
-        member.first_name,
-        member.last_name
+        r#"UPDATE user SET first_name = ?, last_name = ?, username = ?, tg_chat_id = ?, is_active = 1
+        WHERE tg_user_id = ?"#,
+        user.first_name,
+        user.last_name,
+        user.username,
+        chat_id,
+        user.id
     )
-    .fetch_one(&pool)
+    .execute(&pool)
     .await
     {
-        Ok(_) => Ok(true),
-        Err(e) => match e {
-            error::Error::RowNotFound => Ok(false),
-            _ => Err(e.into()),
-        },
+        Ok(res) => Ok(res.rows_affected() > 0),
+        Err(e) => Err(anyhow!(e)),
+    }
+}
+
+// Mark user as inactive and drop messages, that are still waiting for them in the outbox
+pub async fn deactivate_user(pool: Pool<Sqlite>, tg_user_id: i64) -> Result<(), anyhow::Error> {
+    let time_now = chrono::Utc::now().naive_utc();
+    let mut tx = pool.begin().await?;
+    sqlx::query!(
+        r#"UPDATE user SET is_active = 0 WHERE tg_user_id = ?"#,
+        tg_user_id
+    )
+    .execute(&mut *tx)
+    .await?;
+    sqlx::query!(
+        r#"UPDATE outbox SET status = ?, last_error = ?, updated_at = ?
+        WHERE user_id = ? AND status = ?"#,
+        outbox::STATUS_FAILED,
+        "user is inactive",
+        time_now,
+        tg_user_id,
+        outbox::STATUS_PENDING
+    )
+    .execute(&mut *tx)
+    .await?;
+    match tx.commit().await {
+        Ok(_) => Ok(()),
+        Err(e) => Err(anyhow!(e)),
     }
 }
 
@@ -83,14 +130,17 @@ pub async fn insert_reported_stream(
     pool: Pool<Sqlite>,
     video: &holodex::model::Video,
     vtuber: &vtuber::Vtuber,
+    channel_name: &str,
 ) -> Result<(), anyhow::Error> {
     let video_id = video.id.to_string();
     let scheduled_time = video.available_at.naive_utc();
     match sqlx::query!(
-        "INSERT INTO reported_stream (video_id, vtuber_id, scheduled_start) VALUES (?, ?, ?)",
+        r#"INSERT INTO reported_stream (video_id, vtuber_id, scheduled_start, channel_name)
+        VALUES (?, ?, ?, ?)"#,
         video_id,
         vtuber.id,
-        scheduled_time
+        scheduled_time,
+        channel_name
     )
     .execute(&pool)
     .await
@@ -105,15 +155,20 @@ pub async fn insert_vtuber(
     pool: Pool<Sqlite>,
     member: &vtuber::Vtuber,
 ) -> Result<(), anyhow::Error> {
+    let status = member.get_status().to_string();
     match sqlx::query!(
-        r#"INSERT INTO vtuber (first_name, last_name, emoji, wave_name, youtube_handle, youtube_channel_id)
-        VALUES (?, ?, ?, ?, ?, ?)"#,
+        r#"INSERT INTO vtuber (first_name, last_name, emoji, wave_name, youtube_handle, youtube_channel_id,
+            organization, branch, status)
+        VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?)"#,
         member.first_name,
         member.last_name,
         member.emoji,
         member.wave_name,
         member.youtube_handle,
-        member.youtube_channel_id
+        member.youtube_channel_id,
+        member.organization,
+        member.branch,
+        status
     )
     .execute(&pool)
     .await
@@ -139,6 +194,11 @@ fn user_vtubers_from_row(row: SqliteRow) -> UserVtubers {
         emoji: row.get("emoji"),
         youtube_channel_id: row.get("youtube_channel_id"),
         youtube_handle: row.get("youtube_handle"),
+        organization: row.get("organization"),
+        branch: row.get("branch"),
+        is_confirmed: row.get("is_confirmed"),
+        holodex_name: row.get("holodex_name"),
+        status: row.get("status"),
     };
     UserVtubers {
         vtuber: vtuber,
@@ -150,7 +210,9 @@ fn user_vtubers_from_row(row: SqliteRow) -> UserVtubers {
 pub async fn get_wave_members(
     pool: Pool<Sqlite>,
     tg_user_id: i64,
-    wave_name: String,
+    organization: &str,
+    branch: &str,
+    wave_name: &str,
 ) -> Result<Vec<UserVtubers>, anyhow::Error> {
     match sqlx::query(
         r#"SELECT
@@ -161,6 +223,11 @@ pub async fn get_wave_members(
             vtuber.emoji,
             vtuber.youtube_channel_id,
             vtuber.youtube_handle,
+            vtuber.organization,
+            vtuber.branch,
+            vtuber.is_confirmed,
+            vtuber.holodex_name,
+            vtuber.status,
             IIF(user_vtuber.user_id IS NOT NULL, true, false) AS "is_selected" 
         FROM
             vtuber
@@ -173,9 +240,15 @@ pub async fn get_wave_members(
                 user_vtuber.user_id = ?
             ) user_vtuber ON vtuber.id = user_vtuber.vtuber_id
         WHERE
-            vtuber.wave_name = ?;"#,
+            vtuber.organization = ? AND vtuber.branch = ? AND vtuber.wave_name = ?
+            AND vtuber.is_confirmed
+            AND (vtuber.status != 'graduated' OR user_vtuber.user_id IS NOT NULL)
+        ORDER BY
+            vtuber.id;"#,
     )
     .bind(tg_user_id)
+    .bind(organization)
+    .bind(branch)
     .bind(wave_name)
     .fetch_all(&pool)
     .await
@@ -185,40 +258,34 @@ pub async fn get_wave_members(
     }
 }
 
-// Wave, amount of selected members by user and max members amount
+// Group of vtubers (organization, branch or wave), amount of selected members by user
+// and max members amount
 #[derive(Debug)]
-pub struct WaveAmount {
-    pub wave_name: String,
+pub struct GroupAmount {
+    // Any vtuber of the group. Callback data refers to the group by it, because names can be
+    // longer than telegram allows
+    pub vtuber_id: i64,
+    pub name: String,
     pub amount: i64,
     pub max_amount: i64,
 }
 
-// TODO: find some other solution
-// Map waves to debute order
-lazy_static! {
-    static ref NIJIEN_MAP: HashMap<&'static str, i64> = vec![
-        ("LazuLight", 1),
-        ("OBSYDIA", 2),
-        ("Ethyria", 3),
-        ("Luxiem", 4),
-        ("Noctyx", 5),
-        ("ILUNA", 6),
-        ("XSOLEIL", 7),
-        ("Krisis", 8),
-    ]
-    .into_iter()
-    .collect();
-}
-
-// Get waves, amount of selected members and max amount in wave
-pub async fn get_amount_in_waves(
+// Get groups of the level ('organization', 'branch' or 'wave_name' column), amount of
+// selected members and max amount in each. Groups go in catalog order. Filter keeps only
+// vtubers of given organization and branch, if they are set. Graduated vtubers are counted
+// only for their subscribers
+async fn get_amount_in_groups(
     pool: Pool<Sqlite>,
     tg_user_id: i64,
-) -> Result<Vec<WaveAmount>, anyhow::Error> {
-    match sqlx::query(
+    column: &str,
+    organization: Option<&str>,
+    branch: Option<&str>,
+) -> Result<Vec<GroupAmount>, anyhow::Error> {
+    let query = format!(
         r#"
     SELECT
-        vtuber.wave_name,
+        MIN(vtuber.id) AS 'vtuber_id',
+        vtuber.{column} AS 'name',
         COUNT(user_vtuber.id) as 'amount',
         COUNT(vtuber.id) as 'max_amount'
     FROM
@@ -231,32 +298,80 @@ pub async fn get_amount_in_waves(
         WHERE
             user_vtuber.user_id = ?
         ) user_vtuber ON user_vtuber.vtuber_id = vtuber.id
+    WHERE
+        vtuber.is_confirmed
+        AND (vtuber.status != 'graduated' OR user_vtuber.id IS NOT NULL)
+        AND (? IS NULL OR vtuber.organization = ?) AND (? IS NULL OR vtuber.branch = ?)
     GROUP BY
-        vtuber.wave_name;"#,
-    )
-    .bind(tg_user_id)
-    .fetch_all(&pool)
-    .await
+        vtuber.{column}
+    ORDER BY
+        MIN(vtuber.id);"#
+    );
+    match sqlx::query(&query)
+        .bind(tg_user_id)
+        .bind(organization)
+        .bind(organization)
+        .bind(branch)
+        .bind(branch)
+        .fetch_all(&pool)
+        .await
     {
-        Ok(members) => {
-            let mut waves = members
-                .into_iter()
-                .map(|row| WaveAmount {
-                    wave_name: row.get("wave_name"),
-                    amount: row.get("amount"),
-                    max_amount: row.get("max_amount"),
-                })
-                .collect::<Vec<WaveAmount>>();
-            // Sort by debut order
-            waves.sort_by(|x, y| {
-                NIJIEN_MAP[x.wave_name.as_str()].cmp(&NIJIEN_MAP[y.wave_name.as_str()])
-            });
-            Ok(waves)
-        }
+        Ok(groups) => Ok(groups
+            .into_iter()
+            .map(|row| GroupAmount {
+                vtuber_id: row.get("vtuber_id"),
+                name: row.get("name"),
+                amount: row.get("amount"),
+                max_amount: row.get("max_amount"),
+            })
+            .collect()),
         Err(e) => Err(anyhow!(e)),
     }
 }
 
+// Get organizations, amount of selected members and max amount in organization
+pub async fn get_amount_in_organizations(
+    pool: Pool<Sqlite>,
+    tg_user_id: i64,
+) -> Result<Vec<GroupAmount>, anyhow::Error> {
+    get_amount_in_groups(pool, tg_user_id, "organization", None, None).await
+}
+
+// Get branches of organization, amount of selected members and max amount in branch
+pub async fn get_amount_in_branches(
+    pool: Pool<Sqlite>,
+    tg_user_id: i64,
+    organization: &str,
+) -> Result<Vec<GroupAmount>, anyhow::Error> {
+    get_amount_in_groups(pool, tg_user_id, "branch", Some(organization), None).await
+}
+
+// Get waves of branch, amount of selected members and max amount in wave
+pub async fn get_amount_in_waves(
+    pool: Pool<Sqlite>,
+    tg_user_id: i64,
+    organization: &str,
+    branch: &str,
+) -> Result<Vec<GroupAmount>, anyhow::Error> {
+    let order = get_waves(pool.clone(), organization, branch).await?;
+    let mut waves = get_amount_in_groups(
+        pool,
+        tg_user_id,
+        "wave_name",
+        Some(organization),
+        Some(branch),
+    )
+    .await?;
+    // Waves go in display order, unknown ones (e.g. added by sync) keep catalog order
+    waves.sort_by_key(|x| {
+        order
+            .iter()
+            .find(|wave| wave.name == x.name)
+            .map_or(i64::MAX, |wave| wave.display_order)
+    });
+    Ok(waves)
+}
+
 // Update selection for user
 pub async fn update_user_vtuber(
     pool: Pool<Sqlite>,
@@ -293,3 +408,1108 @@ pub async fn update_user_vtuber(
         },
     }
 }
+
+// Get users, subscribed to the vtuber
+pub async fn get_subscribers(
+    pool: Pool<Sqlite>,
+    vtuber_id: i64,
+) -> Result<Vec<crate::user::User>, anyhow::Error> {
+    match sqlx::query_as!(
+        crate::user::User,
+        r#"SELECT user.* FROM user JOIN user_vtuber ON user_vtuber.user_id = user.tg_user_id WHERE user_vtuber.vtuber_id = ? AND user.is_active"#,
+        vtuber_id
+    )
+    .fetch_all(&pool)
+    .await
+    {
+        Ok(users) => Ok(users),
+        Err(e) => Err(anyhow!(e)),
+    }
+}
+
+// Get lead times (in minutes), selected by user
+pub async fn get_user_lead_times(
+    pool: Pool<Sqlite>,
+    tg_user_id: i64,
+) -> Result<Vec<i64>, anyhow::Error> {
+    match sqlx::query!(
+        r#"SELECT minutes FROM user_lead_time WHERE user_id = ? ORDER BY minutes"#,
+        tg_user_id
+    )
+    .fetch_all(&pool)
+    .await
+    {
+        Ok(rows) => Ok(rows.into_iter().map(|row| row.minutes).collect()),
+        Err(e) => Err(anyhow!(e)),
+    }
+}
+
+// Update lead time selection for user
+pub async fn update_user_lead_time(
+    pool: Pool<Sqlite>,
+    tg_user_id: i64,
+    minutes: i64,
+) -> Result<(), anyhow::Error> {
+    match sqlx::query!(
+        r#"SELECT id FROM user_lead_time
+        WHERE user_id = ? AND minutes = ?"#,
+        tg_user_id,
+        minutes
+    )
+    .fetch_one(&pool)
+    .await
+    {
+        Ok(row) => {
+            sqlx::query!(r#"DELETE FROM user_lead_time WHERE id = ?"#, row.id)
+                .execute(&pool)
+                .await?;
+            Ok(())
+        }
+        Err(e) => match e {
+            error::Error::RowNotFound => {
+                sqlx::query!(
+                    r#"INSERT INTO user_lead_time (user_id, minutes) VALUES (?, ?)"#,
+                    tg_user_id,
+                    minutes
+                )
+                .execute(&pool)
+                .await?;
+                Ok(())
+            }
+            _ => Err(e.into()),
+        },
+    }
+}
+
+// Get reminder stages, that user was already notified with about the video
+pub async fn get_reported_stages(
+    pool: Pool<Sqlite>,
+    tg_user_id: i64,
+    video_id: &str,
+) -> Result<Vec<ReminderStage>, anyhow::Error> {
+    match sqlx::query!(
+        r#"SELECT stage FROM reported_notification
+        WHERE user_id = ? AND video_id = ?"#,
+        tg_user_id,
+        video_id
+    )
+    .fetch_all(&pool)
+    .await
+    {
+        Ok(rows) => Ok(rows
+            .into_iter()
+            .filter_map(|row| row.stage.parse().ok())
+            .collect()),
+        Err(e) => Err(anyhow!(e)),
+    }
+}
+
+// Switch 'started' reminder for user
+pub async fn toggle_user_started_ping(
+    pool: Pool<Sqlite>,
+    tg_user_id: i64,
+) -> Result<(), anyhow::Error> {
+    match sqlx::query!(
+        r#"UPDATE user SET started_ping = NOT started_ping WHERE tg_user_id = ?"#,
+        tg_user_id
+    )
+    .execute(&pool)
+    .await
+    {
+        Ok(_) => Ok(()),
+        Err(e) => Err(anyhow!(e)),
+    }
+}
+
+// Get user by telegram id
+pub async fn get_user(
+    pool: Pool<Sqlite>,
+    tg_user_id: i64,
+) -> Result<crate::user::User, anyhow::Error> {
+    match sqlx::query_as!(
+        crate::user::User,
+        r#"SELECT * FROM user WHERE tg_user_id = ?"#,
+        tg_user_id
+    )
+    .fetch_one(&pool)
+    .await
+    {
+        Ok(user) => Ok(user),
+        Err(e) => Err(anyhow!(e)),
+    }
+}
+
+// Set timezone for user
+pub async fn update_user_timezone(
+    pool: Pool<Sqlite>,
+    tg_user_id: i64,
+    timezone: &str,
+) -> Result<(), anyhow::Error> {
+    match sqlx::query!(
+        r#"UPDATE user SET timezone = ? WHERE tg_user_id = ?"#,
+        timezone,
+        tg_user_id
+    )
+    .execute(&pool)
+    .await
+    {
+        Ok(_) => Ok(()),
+        Err(e) => Err(anyhow!(e)),
+    }
+}
+
+// Get all streams, that users were notified about
+pub async fn get_reported_streams(
+    pool: Pool<Sqlite>,
+) -> Result<Vec<crate::reported_stream::ReportedStream>, anyhow::Error> {
+    match sqlx::query_as!(
+        crate::reported_stream::ReportedStream,
+        r#"SELECT * FROM reported_stream"#
+    )
+    .fetch_all(&pool)
+    .await
+    {
+        Ok(streams) => Ok(streams),
+        Err(e) => Err(anyhow!(e)),
+    }
+}
+
+// Get users, that were notified about the video at least once
+pub async fn get_notified_users(
+    pool: Pool<Sqlite>,
+    video_id: &str,
+) -> Result<Vec<crate::user::User>, anyhow::Error> {
+    match sqlx::query_as!(
+        crate::user::User,
+        r#"SELECT DISTINCT user.* FROM user
+        JOIN reported_notification ON reported_notification.user_id = user.tg_user_id
+        WHERE reported_notification.video_id = ? AND user.is_active"#,
+        video_id
+    )
+    .fetch_all(&pool)
+    .await
+    {
+        Ok(users) => Ok(users),
+        Err(e) => Err(anyhow!(e)),
+    }
+}
+
+// Get vtuber by id
+pub async fn get_vtuber(pool: Pool<Sqlite>, vtuber_id: i64) -> Result<Vtuber, anyhow::Error> {
+    match sqlx::query_as!(Vtuber, r#"SELECT * FROM vtuber WHERE id = ?"#, vtuber_id)
+        .fetch_one(&pool)
+        .await
+    {
+        Ok(vtuber) => Ok(vtuber),
+        Err(e) => Err(anyhow!(e)),
+    }
+}
+
+// Save amount of polls in a row, that didn't return the stream
+pub async fn update_missed_polls(
+    pool: Pool<Sqlite>,
+    stream_id: i64,
+    missed_polls: i64,
+) -> Result<(), anyhow::Error> {
+    match sqlx::query!(
+        r#"UPDATE reported_stream SET missed_polls = ? WHERE id = ?"#,
+        missed_polls,
+        stream_id
+    )
+    .execute(&pool)
+    .await
+    {
+        Ok(_) => Ok(()),
+        Err(e) => Err(anyhow!(e)),
+    }
+}
+
+// Save new start of the stream and forget sent reminders, so reminder cycle starts again.
+// Notices about new start are put into outbox for users, that got reminders
+pub async fn reschedule_reported_stream(
+    pool: Pool<Sqlite>,
+    video: &holodex::model::Video,
+    notices: &[(crate::user::User, String)],
+) -> Result<(), anyhow::Error> {
+    let video_id = video.id.to_string();
+    let scheduled_time = video.available_at.naive_utc();
+    let mut tx = pool.begin().await?;
+    sqlx::query!(
+        r#"UPDATE reported_stream SET scheduled_start = ? WHERE video_id = ?"#,
+        scheduled_time,
+        video_id
+    )
+    .execute(&mut *tx)
+    .await?;
+    sqlx::query!(
+        r#"DELETE FROM reported_notification WHERE video_id = ?"#,
+        video_id
+    )
+    .execute(&mut *tx)
+    .await?;
+    drop_pending_outbox(&mut tx, &video_id, "rescheduled").await?;
+    insert_outbox_notices(
+        &mut tx,
+        &video_id,
+        outbox::NOTICE_RESCHEDULED,
+        scheduled_time,
+        notices,
+    )
+    .await?;
+    match tx.commit().await {
+        Ok(_) => Ok(()),
+        Err(e) => Err(anyhow!(e)),
+    }
+}
+
+// Don't deliver pending messages about the video, they are outdated. Entries, that are
+// delivered with these messages, are dropped too
+async fn drop_pending_outbox(
+    tx: &mut sqlx::Transaction<'_, Sqlite>,
+    video_id: &str,
+    reason: &str,
+) -> Result<(), anyhow::Error> {
+    let time_now = chrono::Utc::now().naive_utc();
+    match sqlx::query!(
+        r#"UPDATE outbox SET status = ?, last_error = ?, updated_at = ?
+        WHERE status = ? AND (id IN (SELECT id FROM outbox WHERE video_id = ? AND parent_id IS NULL)
+            OR parent_id IN (SELECT id FROM outbox WHERE video_id = ? AND parent_id IS NULL))"#,
+        outbox::STATUS_FAILED,
+        reason,
+        time_now,
+        outbox::STATUS_PENDING,
+        video_id,
+        video_id
+    )
+    .execute(&mut **tx)
+    .await
+    {
+        Ok(_) => Ok(()),
+        Err(e) => Err(anyhow!(e)),
+    }
+}
+
+// Put text notices about the video into outbox, one for every user. Pending messages about
+// the video are dropped before, so notices aren't dropped with them. Notice, that was
+// delivered before (stream was cancelled again), is reset
+async fn insert_outbox_notices(
+    tx: &mut sqlx::Transaction<'_, Sqlite>,
+    video_id: &str,
+    stage: &str,
+    scheduled_start: chrono::NaiveDateTime,
+    notices: &[(crate::user::User, String)],
+) -> Result<(), anyhow::Error> {
+    let time_now = chrono::Utc::now().naive_utc();
+    for (user, text) in notices {
+        sqlx::query!(
+            r#"INSERT INTO outbox (user_id, chat_id, video_id, stage, scheduled_start,
+                text, status, next_attempt_at, created_at, updated_at)
+            VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
+            ON CONFLICT(user_id, video_id, stage, scheduled_start) DO UPDATE SET
+                chat_id = excluded.chat_id, parent_id = excluded.parent_id,
+                photo_url = excluded.photo_url, text = excluded.text, status = excluded.status,
+                attempts = 0, last_error = NULL, next_attempt_at = excluded.next_attempt_at,
+                updated_at = excluded.updated_at"#,
+            user.tg_user_id,
+            user.tg_chat_id,
+            video_id,
+            stage,
+            scheduled_start,
+            text,
+            outbox::STATUS_PENDING,
+            time_now,
+            time_now,
+            time_now
+        )
+        .execute(&mut **tx)
+        .await?;
+    }
+    Ok(())
+}
+
+// Forget the stream and reminders, that were sent about it. Notices about cancel are put
+// into outbox for users, that got reminders
+pub async fn delete_reported_stream(
+    pool: Pool<Sqlite>,
+    video_id: &str,
+    scheduled_start: chrono::NaiveDateTime,
+    notices: &[(crate::user::User, String)],
+) -> Result<(), anyhow::Error> {
+    let mut tx = pool.begin().await?;
+    sqlx::query!(
+        r#"DELETE FROM reported_notification WHERE video_id = ?"#,
+        video_id
+    )
+    .execute(&mut *tx)
+    .await?;
+    sqlx::query!(
+        r#"DELETE FROM reported_stream WHERE video_id = ?"#,
+        video_id
+    )
+    .execute(&mut *tx)
+    .await?;
+    drop_pending_outbox(&mut tx, video_id, "cancelled").await?;
+    insert_outbox_notices(
+        &mut tx,
+        video_id,
+        outbox::NOTICE_CANCELLED,
+        scheduled_start,
+        notices,
+    )
+    .await?;
+    match tx.commit().await {
+        Ok(_) => Ok(()),
+        Err(e) => Err(anyhow!(e)),
+    }
+}
+
+// Get kinds of videos, selected by user
+pub async fn get_user_video_kinds(
+    pool: Pool<Sqlite>,
+    tg_user_id: i64,
+) -> Result<Vec<VideoKind>, anyhow::Error> {
+    match sqlx::query!(
+        r#"SELECT kind FROM user_video_kind WHERE user_id = ?"#,
+        tg_user_id
+    )
+    .fetch_all(&pool)
+    .await
+    {
+        Ok(rows) => Ok(rows
+            .into_iter()
+            .filter_map(|row| row.kind.parse().ok())
+            .collect()),
+        Err(e) => Err(anyhow!(e)),
+    }
+}
+
+// Update video kind selection for user
+pub async fn update_user_video_kind(
+    pool: Pool<Sqlite>,
+    tg_user_id: i64,
+    kind: VideoKind,
+) -> Result<(), anyhow::Error> {
+    let kind = kind.to_string();
+    match sqlx::query!(
+        r#"SELECT id FROM user_video_kind
+        WHERE user_id = ? AND kind = ?"#,
+        tg_user_id,
+        kind
+    )
+    .fetch_one(&pool)
+    .await
+    {
+        Ok(row) => {
+            sqlx::query!(r#"DELETE FROM user_video_kind WHERE id = ?"#, row.id)
+                .execute(&pool)
+                .await?;
+            Ok(())
+        }
+        Err(e) => match e {
+            error::Error::RowNotFound => {
+                sqlx::query!(
+                    r#"INSERT INTO user_video_kind (user_id, kind) VALUES (?, ?)"#,
+                    tg_user_id,
+                    kind
+                )
+                .execute(&pool)
+                .await?;
+                Ok(())
+            }
+            _ => Err(e.into()),
+        },
+    }
+}
+
+// Get topics, selected by user
+pub async fn get_user_topics(
+    pool: Pool<Sqlite>,
+    tg_user_id: i64,
+) -> Result<Vec<String>, anyhow::Error> {
+    match sqlx::query!(
+        r#"SELECT topic FROM user_topic WHERE user_id = ?"#,
+        tg_user_id
+    )
+    .fetch_all(&pool)
+    .await
+    {
+        Ok(rows) => Ok(rows.into_iter().map(|row| row.topic).collect()),
+        Err(e) => Err(anyhow!(e)),
+    }
+}
+
+// Update topic selection for user
+pub async fn update_user_topic(
+    pool: Pool<Sqlite>,
+    tg_user_id: i64,
+    topic: &str,
+) -> Result<(), anyhow::Error> {
+    match sqlx::query!(
+        r#"SELECT id FROM user_topic
+        WHERE user_id = ? AND topic = ?"#,
+        tg_user_id,
+        topic
+    )
+    .fetch_one(&pool)
+    .await
+    {
+        Ok(row) => {
+            sqlx::query!(r#"DELETE FROM user_topic WHERE id = ?"#, row.id)
+                .execute(&pool)
+                .await?;
+            Ok(())
+        }
+        Err(e) => match e {
+            error::Error::RowNotFound => {
+                sqlx::query!(
+                    r#"INSERT INTO user_topic (user_id, topic) VALUES (?, ?)"#,
+                    tg_user_id,
+                    topic
+                )
+                .execute(&pool)
+                .await?;
+                Ok(())
+            }
+            _ => Err(e.into()),
+        },
+    }
+}
+
+// Set topic mode for user
+pub async fn update_user_topic_mode(
+    pool: Pool<Sqlite>,
+    tg_user_id: i64,
+    mode: TopicMode,
+) -> Result<(), anyhow::Error> {
+    let mode = mode.to_string();
+    match sqlx::query!(
+        r#"UPDATE user SET topic_mode = ? WHERE tg_user_id = ?"#,
+        mode,
+        tg_user_id
+    )
+    .execute(&pool)
+    .await
+    {
+        Ok(_) => Ok(()),
+        Err(e) => Err(anyhow!(e)),
+    }
+}
+
+// Switch digest mode for user
+pub async fn toggle_user_digest_mode(
+    pool: Pool<Sqlite>,
+    tg_user_id: i64,
+) -> Result<(), anyhow::Error> {
+    match sqlx::query!(
+        r#"UPDATE user SET digest_mode = NOT digest_mode WHERE tg_user_id = ?"#,
+        tg_user_id
+    )
+    .execute(&pool)
+    .await
+    {
+        Ok(_) => Ok(()),
+        Err(e) => Err(anyhow!(e)),
+    }
+}
+
+// Get vtubers, that user is subscribed to
+pub async fn get_user_vtubers(
+    pool: Pool<Sqlite>,
+    tg_user_id: i64,
+) -> Result<Vec<Vtuber>, anyhow::Error> {
+    match sqlx::query_as!(
+        Vtuber,
+        r#"SELECT vtuber.* FROM vtuber JOIN user_vtuber ON user_vtuber.vtuber_id = vtuber.id WHERE user_vtuber.user_id = ?"#,
+        tg_user_id
+    )
+    .fetch_all(&pool)
+    .await
+    {
+        Ok(vtubers) => Ok(vtubers),
+        Err(e) => Err(anyhow!(e)),
+    }
+}
+
+// Get users, that receive daily or weekly schedule digest
+pub async fn get_digest_users(pool: Pool<Sqlite>) -> Result<Vec<crate::user::User>, anyhow::Error> {
+    match sqlx::query_as!(
+        crate::user::User,
+        r#"SELECT * FROM user WHERE is_active AND (digest_time IS NOT NULL OR weekly_digest)"#
+    )
+    .fetch_all(&pool)
+    .await
+    {
+        Ok(users) => Ok(users),
+        Err(e) => Err(anyhow!(e)),
+    }
+}
+
+// Mark digest for the period as sent. Returns false, if it was already sent
+pub async fn claim_digest(
+    pool: Pool<Sqlite>,
+    tg_user_id: i64,
+    kind: &str,
+    period: &str,
+) -> Result<bool, anyhow::Error> {
+    match sqlx::query!(
+        r#"INSERT OR IGNORE INTO sent_digest (user_id, kind, period) VALUES (?, ?, ?)"#,
+        tg_user_id,
+        kind,
+        period
+    )
+    .execute(&pool)
+    .await
+    {
+        Ok(res) => Ok(res.rows_affected() > 0),
+        Err(e) => Err(anyhow!(e)),
+    }
+}
+
+// Forget digest for the period, so it is sent again
+pub async fn release_digest(
+    pool: Pool<Sqlite>,
+    tg_user_id: i64,
+    kind: &str,
+    period: &str,
+) -> Result<(), anyhow::Error> {
+    match sqlx::query!(
+        r#"DELETE FROM sent_digest WHERE user_id = ? AND kind = ? AND period = ?"#,
+        tg_user_id,
+        kind,
+        period
+    )
+    .execute(&pool)
+    .await
+    {
+        Ok(_) => Ok(()),
+        Err(e) => Err(anyhow!(e)),
+    }
+}
+
+// Set local time of daily digest for user. None disables digest
+pub async fn update_user_digest_time(
+    pool: Pool<Sqlite>,
+    tg_user_id: i64,
+    digest_time: Option<&str>,
+) -> Result<(), anyhow::Error> {
+    match sqlx::query!(
+        r#"UPDATE user SET digest_time = ? WHERE tg_user_id = ?"#,
+        digest_time,
+        tg_user_id
+    )
+    .execute(&pool)
+    .await
+    {
+        Ok(_) => Ok(()),
+        Err(e) => Err(anyhow!(e)),
+    }
+}
+
+// Switch weekly digest for user
+pub async fn toggle_user_weekly_digest(
+    pool: Pool<Sqlite>,
+    tg_user_id: i64,
+) -> Result<(), anyhow::Error> {
+    match sqlx::query!(
+        r#"UPDATE user SET weekly_digest = NOT weekly_digest WHERE tg_user_id = ?"#,
+        tg_user_id
+    )
+    .execute(&pool)
+    .await
+    {
+        Ok(_) => Ok(()),
+        Err(e) => Err(anyhow!(e)),
+    }
+}
+
+// Put message into outbox and mark its reminder stages as reported. Both are done in one
+// transaction, so stage is neither lost nor sent twice after restart. Entry of the stage, that
+// was delivered before (stream came back after cancel or moved back to its start), is reset
+pub async fn enqueue_outbox_message(
+    pool: Pool<Sqlite>,
+    user: &crate::user::User,
+    message: &OutboxMessage,
+) -> Result<(), anyhow::Error> {
+    let time_now = chrono::Utc::now().naive_utc();
+    let mut tx = pool.begin().await?;
+    let mut parent_id: Option<i64> = None;
+    for item in &message.items {
+        let stage = item.stage.to_string();
+        sqlx::query!(
+            r#"INSERT OR IGNORE INTO reported_notification (video_id, user_id, stage, scheduled_start)
+            VALUES (?, ?, ?, ?)"#,
+            item.video_id,
+            user.tg_user_id,
+            stage,
+            item.scheduled_start
+        )
+        .execute(&mut *tx)
+        .await?;
+        // Message itself is kept in the parent entry only
+        let (photo_url, text) = match parent_id {
+            Some(_) => (None, String::new()),
+            None => (message.photo_url.clone(), message.text.clone()),
+        };
+        let id = sqlx::query!(
+            r#"INSERT INTO outbox (user_id, chat_id, video_id, stage, scheduled_start, parent_id,
+                photo_url, text, status, next_attempt_at, created_at, updated_at)
+            VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
+            ON CONFLICT(user_id, video_id, stage, scheduled_start) DO UPDATE SET
+                chat_id = excluded.chat_id, parent_id = excluded.parent_id,
+                photo_url = excluded.photo_url, text = excluded.text, status = excluded.status,
+                attempts = 0, last_error = NULL, next_attempt_at = excluded.next_attempt_at,
+                updated_at = excluded.updated_at
+            RETURNING id"#,
+            user.tg_user_id,
+            user.tg_chat_id,
+            item.video_id,
+            stage,
+            item.scheduled_start,
+            parent_id,
+            photo_url,
+            text,
+            outbox::STATUS_PENDING,
+            time_now,
+            time_now,
+            time_now
+        )
+        .fetch_one(&mut *tx)
+        .await?
+        .id;
+        parent_id = parent_id.or(Some(id));
+    }
+    match tx.commit().await {
+        Ok(_) => Ok(()),
+        Err(e) => Err(anyhow!(e)),
+    }
+}
+
+// Get outbox messages, that are ready to be delivered, in order of creation. Messages of the
+// user wait, while their earlier message waits for retry
+pub async fn get_pending_outbox(
+    pool: Pool<Sqlite>,
+    limit: i64,
+) -> Result<Vec<OutboxEntry>, anyhow::Error> {
+    let time_now = chrono::Utc::now().naive_utc();
+    match sqlx::query_as!(
+        OutboxEntry,
+        r#"SELECT * FROM outbox
+        WHERE status = ? AND parent_id IS NULL AND next_attempt_at <= ?
+            AND NOT EXISTS (
+                SELECT 1 FROM outbox AS earlier
+                WHERE earlier.user_id = outbox.user_id AND earlier.status = ?
+                    AND earlier.parent_id IS NULL AND earlier.id < outbox.id
+                    AND earlier.next_attempt_at > ?
+            )
+        ORDER BY id
+        LIMIT ?"#,
+        outbox::STATUS_PENDING,
+        time_now,
+        outbox::STATUS_PENDING,
+        time_now,
+        limit
+    )
+    .fetch_all(&pool)
+    .await
+    {
+        Ok(entries) => Ok(entries),
+        Err(e) => Err(anyhow!(e)),
+    }
+}
+
+// Save delivery result of outbox message and entries, delivered with it
+pub async fn update_outbox_status(
+    pool: Pool<Sqlite>,
+    entry_id: i64,
+    status: &str,
+    attempts: i64,
+    last_error: Option<String>,
+    next_attempt_at: chrono::NaiveDateTime,
+) -> Result<(), anyhow::Error> {
+    let time_now = chrono::Utc::now().naive_utc();
+    match sqlx::query!(
+        r#"UPDATE outbox
+        SET status = ?, attempts = ?, last_error = ?, next_attempt_at = ?, updated_at = ?
+        WHERE id = ? OR parent_id = ?"#,
+        status,
+        attempts,
+        last_error,
+        next_attempt_at,
+        time_now,
+        entry_id,
+        entry_id
+    )
+    .execute(&pool)
+    .await
+    {
+        Ok(_) => Ok(()),
+        Err(e) => Err(anyhow!(e)),
+    }
+}
+
+// Get organizations of the catalog
+pub async fn get_organizations(pool: Pool<Sqlite>) -> Result<Vec<String>, anyhow::Error> {
+    match sqlx::query!(r#"SELECT DISTINCT organization FROM vtuber ORDER BY organization"#)
+        .fetch_all(&pool)
+        .await
+    {
+        Ok(rows) => Ok(rows.into_iter().map(|x| x.organization).collect()),
+        Err(e) => Err(anyhow!(e)),
+    }
+}
+
+// Get vtuber by youtube channel id
+pub async fn get_vtuber_by_channel_id(
+    pool: Pool<Sqlite>,
+    youtube_channel_id: &str,
+) -> Result<Option<Vtuber>, anyhow::Error> {
+    match sqlx::query_as!(
+        Vtuber,
+        r#"SELECT * FROM vtuber WHERE youtube_channel_id = ?"#,
+        youtube_channel_id
+    )
+    .fetch_one(&pool)
+    .await
+    {
+        Ok(vtuber) => Ok(Some(vtuber)),
+        Err(e) => match e {
+            error::Error::RowNotFound => Ok(None),
+            _ => Err(e.into()),
+        },
+    }
+}
+
+// Insert channel, found by holodex sync. It stays hidden from the catalog until admin
+// confirms it. Returns id of the vtuber
+pub async fn insert_synced_vtuber(
+    pool: Pool<Sqlite>,
+    member: &vtuber::Vtuber,
+) -> Result<i64, anyhow::Error> {
+    match sqlx::query!(
+        r#"INSERT INTO vtuber (first_name, last_name, emoji, wave_name, youtube_handle, youtube_channel_id,
+            organization, branch, is_confirmed, holodex_name)
+        VALUES (?, ?, ?, ?, ?, ?, ?, ?, 0, ?)"#,
+        member.first_name,
+        member.last_name,
+        member.emoji,
+        member.wave_name,
+        member.youtube_handle,
+        member.youtube_channel_id,
+        member.organization,
+        member.branch,
+        member.holodex_name
+    )
+    .execute(&pool)
+    .await
+    {
+        Ok(res) => Ok(res.last_insert_rowid()),
+        Err(e) => Err(anyhow!(e)),
+    }
+}
+
+// Save holodex name of the channel from holodex sync
+pub async fn update_vtuber_holodex_name(
+    pool: Pool<Sqlite>,
+    vtuber_id: i64,
+    holodex_name: &str,
+) -> Result<(), anyhow::Error> {
+    match sqlx::query!(
+        r#"UPDATE vtuber SET holodex_name = ? WHERE id = ?"#,
+        holodex_name,
+        vtuber_id
+    )
+    .execute(&pool)
+    .await
+    {
+        Ok(_) => Ok(()),
+        Err(e) => Err(anyhow!(e)),
+    }
+}
+
+// Move vtuber to another organization, confirmed by admin. Branches of the organizations
+// differ, so vtuber is hidden in the given group, until admin places it with /vtuber
+pub async fn move_vtuber_organization(
+    pool: Pool<Sqlite>,
+    vtuber_id: i64,
+    organization: &str,
+    group: &str,
+) -> Result<(), anyhow::Error> {
+    match sqlx::query!(
+        r#"UPDATE vtuber
+        SET organization = ?, branch = ?, wave_name = ?, is_confirmed = 0
+        WHERE id = ?"#,
+        organization,
+        group,
+        group,
+        vtuber_id
+    )
+    .execute(&pool)
+    .await
+    {
+        Ok(_) => Ok(()),
+        Err(e) => Err(anyhow!(e)),
+    }
+}
+
+// Show vtuber in the catalog
+pub async fn confirm_vtuber(pool: Pool<Sqlite>, vtuber_id: i64) -> Result<(), anyhow::Error> {
+    match sqlx::query!(
+        r#"UPDATE vtuber SET is_confirmed = 1 WHERE id = ?"#,
+        vtuber_id
+    )
+    .execute(&pool)
+    .await
+    {
+        Ok(_) => Ok(()),
+        Err(e) => Err(anyhow!(e)),
+    }
+}
+
+// Set name, place in the catalog and emoji of vtuber and confirm it
+pub async fn update_vtuber_catalog_info(
+    pool: Pool<Sqlite>,
+    member: &vtuber::Vtuber,
+) -> Result<(), anyhow::Error> {
+    match sqlx::query!(
+        r#"UPDATE vtuber
+        SET first_name = ?, last_name = ?, branch = ?, wave_name = ?, emoji = ?, is_confirmed = 1
+        WHERE id = ?"#,
+        member.first_name,
+        member.last_name,
+        member.branch,
+        member.wave_name,
+        member.emoji,
+        member.id
+    )
+    .execute(&pool)
+    .await
+    {
+        Ok(_) => Ok(()),
+        Err(e) => Err(anyhow!(e)),
+    }
+}
+
+// Set lifecycle status of vtuber. Subscribers are notified about it by status notices.
+// Returning vtuber forgets sent notices, so the next hiatus is announced again
+pub async fn update_vtuber_status(
+    pool: Pool<Sqlite>,
+    vtuber_id: i64,
+    status: VtuberStatus,
+) -> Result<(), anyhow::Error> {
+    let is_active = status == VtuberStatus::Active;
+    let status = status.to_string();
+    let mut tx = pool.begin().await?;
+    sqlx::query!(
+        r#"UPDATE vtuber SET status = ? WHERE id = ?"#,
+        status,
+        vtuber_id
+    )
+    .execute(&mut *tx)
+    .await?;
+    if is_active {
+        sqlx::query!(
+            r#"DELETE FROM vtuber_status_notice WHERE vtuber_id = ?"#,
+            vtuber_id
+        )
+        .execute(&mut *tx)
+        .await?;
+    }
+    match tx.commit().await {
+        Ok(_) => Ok(()),
+        Err(e) => Err(anyhow!(e)),
+    }
+}
+
+// Get vtubers, that aren't active
+pub async fn get_inactive_vtubers(pool: Pool<Sqlite>) -> Result<Vec<Vtuber>, anyhow::Error> {
+    match sqlx::query_as!(Vtuber, r#"SELECT * FROM vtuber WHERE status != 'active'"#)
+        .fetch_all(&pool)
+        .await
+    {
+        Ok(vtubers) => Ok(vtubers),
+        Err(e) => Err(anyhow!(e)),
+    }
+}
+
+// Mark status notice as sent to user. Returns false, if it was already sent
+pub async fn claim_status_notice(
+    pool: Pool<Sqlite>,
+    tg_user_id: i64,
+    vtuber: &Vtuber,
+) -> Result<bool, anyhow::Error> {
+    match sqlx::query!(
+        r#"INSERT OR IGNORE INTO vtuber_status_notice (user_id, vtuber_id, status) VALUES (?, ?, ?)"#,
+        tg_user_id,
+        vtuber.id,
+        vtuber.status
+    )
+    .execute(&pool)
+    .await
+    {
+        Ok(res) => Ok(res.rows_affected() > 0),
+        Err(e) => Err(anyhow!(e)),
+    }
+}
+
+// Forget status notice of user, so it is sent again
+pub async fn release_status_notice(
+    pool: Pool<Sqlite>,
+    tg_user_id: i64,
+    vtuber: &Vtuber,
+) -> Result<(), anyhow::Error> {
+    match sqlx::query!(
+        r#"DELETE FROM vtuber_status_notice WHERE user_id = ? AND vtuber_id = ? AND status = ?"#,
+        tg_user_id,
+        vtuber.id,
+        vtuber.status
+    )
+    .execute(&pool)
+    .await
+    {
+        Ok(_) => Ok(()),
+        Err(e) => Err(anyhow!(e)),
+    }
+}
+
+// Remove vtuber from selection of user
+pub async fn delete_user_vtuber(
+    pool: Pool<Sqlite>,
+    tg_user_id: i64,
+    vtuber_id: i64,
+) -> Result<(), anyhow::Error> {
+    match sqlx::query!(
+        r#"DELETE FROM user_vtuber WHERE user_id = ? AND vtuber_id = ?"#,
+        tg_user_id,
+        vtuber_id
+    )
+    .execute(&pool)
+    .await
+    {
+        Ok(_) => Ok(()),
+        Err(e) => Err(anyhow!(e)),
+    }
+}
+
+// Get waves of the branch in display order
+pub async fn get_waves(
+    pool: Pool<Sqlite>,
+    organization: &str,
+    branch: &str,
+) -> Result<Vec<vtuber::Wave>, anyhow::Error> {
+    match sqlx::query_as!(
+        vtuber::Wave,
+        r#"SELECT * FROM wave WHERE organization = ? AND branch = ? ORDER BY display_order"#,
+        organization,
+        branch
+    )
+    .fetch_all(&pool)
+    .await
+    {
+        Ok(waves) => Ok(waves),
+        Err(e) => Err(anyhow!(e)),
+    }
+}
+
+// Get wave by its name in the branch
+pub async fn get_wave(
+    pool: Pool<Sqlite>,
+    organization: &str,
+    branch: &str,
+    name: &str,
+) -> Result<Option<vtuber::Wave>, anyhow::Error> {
+    match sqlx::query_as!(
+        vtuber::Wave,
+        r#"SELECT * FROM wave WHERE organization = ? AND branch = ? AND name = ?"#,
+        organization,
+        branch,
+        name
+    )
+    .fetch_one(&pool)
+    .await
+    {
+        Ok(wave) => Ok(Some(wave)),
+        Err(e) => match e {
+            error::Error::RowNotFound => Ok(None),
+            _ => Err(e.into()),
+        },
+    }
+}
+
+// Insert wave or update its display order, debut date and description
+pub async fn upsert_wave(pool: Pool<Sqlite>, wave: &vtuber::Wave) -> Result<(), anyhow::Error> {
+    match sqlx::query!(
+        r#"INSERT INTO wave (organization, branch, name, display_order, debut_date, description)
+        VALUES (?, ?, ?, ?, ?, ?)
+        ON CONFLICT(organization, branch, name) DO UPDATE SET
+            display_order = excluded.display_order,
+            debut_date = excluded.debut_date,
+            description = excluded.description"#,
+        wave.organization,
+        wave.branch,
+        wave.name,
+        wave.display_order,
+        wave.debut_date,
+        wave.description
+    )
+    .execute(&pool)
+    .await
+    {
+        Ok(_) => Ok(()),
+        Err(e) => Err(anyhow!(e)),
+    }
+}
+
+// Get all vtubers of the catalog
+pub async fn get_vtubers(pool: Pool<Sqlite>) -> Result<Vec<Vtuber>, anyhow::Error> {
+    match sqlx::query_as!(Vtuber, r#"SELECT * FROM vtuber"#)
+        .fetch_all(&pool)
+        .await
+    {
+        Ok(vtubers) => Ok(vtubers),
+        Err(e) => Err(anyhow!(e)),
+    }
+}
+
+// Get vtubers with the name
+pub async fn get_vtubers_by_name(
+    pool: Pool<Sqlite>,
+    first_name: &str,
+    last_name: &str,
+) -> Result<Vec<Vtuber>, anyhow::Error> {
+    match sqlx::query_as!(
+        Vtuber,
+        r#"SELECT * FROM vtuber WHERE first_name = ? AND last_name = ?"#,
+        first_name,
+        last_name
+    )
+    .fetch_all(&pool)
+    .await
+    {
+        Ok(vtubers) => Ok(vtubers),
+        Err(e) => Err(anyhow!(e)),
+    }
+}
+
+// Update vtuber from catalog (everything except status) and confirm it
+pub async fn update_vtuber(pool: Pool<Sqlite>, member: &Vtuber) -> Result<(), anyhow::Error> {
+    match sqlx::query!(
+        r#"UPDATE vtuber
+        SET first_name = ?, last_name = ?, emoji = ?, youtube_handle = ?, organization = ?,
+            branch = ?, wave_name = ?, is_confirmed = 1
+        WHERE id = ?"#,
+        member.first_name,
+        member.last_name,
+        member.emoji,
+        member.youtube_handle,
+        member.organization,
+        member.branch,
+        member.wave_name,
+        member.id
+    )
+    .execute(&pool)
+    .await
+    {
+        Ok(_) => Ok(()),
+        Err(e) => Err(anyhow!(e)),
+    }
+}
@@ -0,0 +1,185 @@
+use std::fmt;
+
+// Lead times (in minutes) that user can pick for reminders
+pub const LEAD_TIME_OPTIONS: [i64; 4] = [5, 15, 30, 60];
+
+// Lead time for new users
+pub const DEFAULT_LEAD_TIME: i64 = 15;
+
+// How long after going live the stream is still tracked for 'started' reminder
+pub const LIVE_GRACE_MINUTES: i64 = 15;
+
+// How long after scheduled start the stream is tracked, while it isn't live yet
+pub const LATE_START_HOURS: i64 = 3;
+
+// Stage of the reminder schedule for a stream. Every stage is reported once per user
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ReminderStage {
+    // Reminder some minutes before the start
+    Before(i64),
+    // Reminder, when stream actually went live
+    Started,
+}
+
+impl fmt::Display for ReminderStage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ReminderStage::Before(minutes) => write!(f, "before_{}", minutes),
+            ReminderStage::Started => write!(f, "started"),
+        }
+    }
+}
+
+impl std::str::FromStr for ReminderStage {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "started" => Ok(ReminderStage::Started),
+            _ => match s.strip_prefix("before_") {
+                Some(minutes) => Ok(ReminderStage::Before(minutes.parse()?)),
+                None => Err(anyhow::anyhow!("Unknown reminder stage: {}", s)),
+            },
+        }
+    }
+}
+
+// Reminder schedule of the user: lead times and optional 'started' reminder
+pub fn user_stages(lead_times: &[i64], started_ping: bool) -> Vec<ReminderStage> {
+    let mut stages = lead_times
+        .iter()
+        .map(|x| ReminderStage::Before(*x))
+        .collect::<Vec<_>>();
+    if started_ping {
+        stages.push(ReminderStage::Started);
+    }
+    stages
+}
+
+// Get stages, that are due for the stream and weren't reported yet. Reminders before the
+// start make no sense for live stream, and 'started' waits for the stream to go live
+pub fn due_stages(
+    time_left: i64,
+    is_live: bool,
+    stages: &[ReminderStage],
+    reported: &[ReminderStage],
+) -> Vec<ReminderStage> {
+    stages
+        .iter()
+        .filter(|stage| match stage {
+            ReminderStage::Before(minutes) => !is_live && time_left <= *minutes,
+            ReminderStage::Started => is_live,
+        })
+        .filter(|stage| !reported.contains(stage))
+        .cloned()
+        .collect()
+}
+
+// Pick the stage to build message from: the latest one of due stages
+pub fn latest_stage(due: &[ReminderStage]) -> Option<ReminderStage> {
+    match due.contains(&ReminderStage::Started) {
+        true => Some(ReminderStage::Started),
+        false => due
+            .iter()
+            .filter_map(|stage| match stage {
+                ReminderStage::Before(minutes) => Some(*minutes),
+                ReminderStage::Started => None,
+            })
+            .min()
+            .map(ReminderStage::Before),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn stage_round_trips_through_string() {
+        for stage in [ReminderStage::Before(15), ReminderStage::Started] {
+            assert_eq!(stage.to_string().parse::<ReminderStage>().unwrap(), stage);
+        }
+        assert!("after_5".parse::<ReminderStage>().is_err());
+        assert!("before_x".parse::<ReminderStage>().is_err());
+    }
+
+    #[test]
+    fn started_stage_is_added_by_opt_in() {
+        assert_eq!(
+            user_stages(&[30, 5], false),
+            vec![ReminderStage::Before(30), ReminderStage::Before(5)]
+        );
+        assert_eq!(
+            user_stages(&[5], true),
+            vec![ReminderStage::Before(5), ReminderStage::Started]
+        );
+    }
+
+    #[test]
+    fn before_stage_is_due_within_lead_time() {
+        let stages = user_stages(&[60, 15], true);
+        assert_eq!(
+            due_stages(40, false, &stages, &[]),
+            vec![ReminderStage::Before(60)]
+        );
+        assert!(due_stages(90, false, &stages, &[]).is_empty());
+    }
+
+    #[test]
+    fn late_announced_stream_collapses_stages() {
+        // Stream appeared 3 minutes before start: every 'before' stage is due at once
+        let stages = user_stages(&[60, 30, 15, 5], true);
+        let due = due_stages(3, false, &stages, &[]);
+        assert_eq!(
+            due,
+            vec![
+                ReminderStage::Before(60),
+                ReminderStage::Before(30),
+                ReminderStage::Before(15),
+                ReminderStage::Before(5)
+            ]
+        );
+        assert_eq!(latest_stage(&due), Some(ReminderStage::Before(5)));
+    }
+
+    #[test]
+    fn reported_stages_are_not_due_again() {
+        let stages = user_stages(&[30, 5], false);
+        let reported = [ReminderStage::Before(30)];
+        assert_eq!(
+            due_stages(4, false, &stages, &reported),
+            vec![ReminderStage::Before(5)]
+        );
+        assert!(due_stages(4, false, &stages, &stages).is_empty());
+    }
+
+    #[test]
+    fn live_stream_gets_only_started_stage() {
+        let stages = user_stages(&[15, 5], true);
+        // Stream went live before its scheduled start
+        let due = due_stages(2, true, &stages, &[]);
+        assert_eq!(due, vec![ReminderStage::Started]);
+        assert_eq!(latest_stage(&due), Some(ReminderStage::Started));
+        // Without opt-in live stream has nothing to remind about
+        assert!(due_stages(2, true, &user_stages(&[15, 5], false), &[]).is_empty());
+    }
+
+    #[test]
+    fn started_stage_waits_for_live() {
+        let stages = user_stages(&[5], true);
+        // Stream is late, but not live yet
+        assert_eq!(
+            due_stages(-10, false, &stages, &[ReminderStage::Before(5)]),
+            vec![]
+        );
+    }
+
+    #[test]
+    fn latest_stage_prefers_started() {
+        assert_eq!(
+            latest_stage(&[ReminderStage::Before(5), ReminderStage::Started]),
+            Some(ReminderStage::Started)
+        );
+        assert_eq!(latest_stage(&[]), None);
+    }
+}
@@ -2,6 +2,10 @@ use chrono;
 use serde;
 use serde::Deserialize;
 
+// Polls in a row, that have to miss the stream, before it's considered deleted. Holodex
+// drops videos from responses from time to time
+pub const MISSES_TO_CANCEL: i64 = 3;
+
 // Stream that users were notified about
 #[derive(Deserialize, Clone, Debug, Default)]
 pub struct ReportedStream {
@@ -9,6 +13,41 @@ pub struct ReportedStream {
     pub id: i64,
     // Youtube video id
     pub video_id: String,
+    // Host of the stream or, if the host isn't in db, first guest
     pub vtuber_id: i64,
     pub scheduled_start: chrono::NaiveDateTime,
+    // Name of the channel, where the stream takes place
+    pub channel_name: String,
+    // Polls in a row, that didn't return the stream
+    pub missed_polls: i64,
+}
+
+impl ReportedStream {
+    // Count the poll, that didn't return the stream. Returns true, if the stream is missing
+    // long enough to be considered deleted
+    pub fn miss(&mut self) -> bool {
+        self.missed_polls += 1;
+        self.missed_polls >= MISSES_TO_CANCEL
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn stream_absent_once_is_not_cancelled() {
+        let mut stream = ReportedStream::default();
+        assert!(!stream.miss());
+        assert_eq!(stream.missed_polls, 1);
+    }
+
+    #[test]
+    fn stream_absent_in_a_row_is_cancelled() {
+        let mut stream = ReportedStream::default();
+        for _ in 1..MISSES_TO_CANCEL {
+            assert!(!stream.miss());
+        }
+        assert!(stream.miss());
+    }
 }
@@ -0,0 +1,221 @@
+use chrono::Datelike;
+use holodex::model::{
+    builders::VideoFilterBuilder, ChannelId, Video, VideoSortingCriteria, VideoStatus,
+};
+use log::warn;
+use std::sync::Arc;
+use tokio::task::JoinSet;
+
+use crate::holodex_request;
+use crate::timezone;
+use crate::vtuber::Vtuber;
+
+// Channels, whose schedule is requested at the same time
+const CHANNEL_BATCH_SIZE: usize = 5;
+
+// Fetch upcoming videos of vtubers within the horizon. Every channel is a separate request
+// with its own timeout, so a long list of subscriptions doesn't hit one timeout. Channels,
+// that failed, are skipped, unless all of them failed
+pub async fn fetch_schedule(
+    holodex_api: Arc<holodex::Client>,
+    vtubers: Vec<Vtuber>,
+    horizon_hours: u32,
+) -> anyhow::Result<Vec<(Vtuber, Video)>> {
+    let mut schedule = Vec::new();
+    let mut failed = 0;
+    let mut last_error = None;
+    for batch in vtubers.chunks(CHANNEL_BATCH_SIZE) {
+        let mut tasks = JoinSet::new();
+        for vtuber in batch {
+            let vtuber = vtuber.clone();
+            let holodex_api = holodex_api.clone();
+            tasks.spawn(async move {
+                let res = fetch_channel_schedule(holodex_api, &vtuber, horizon_hours).await;
+                (vtuber, res)
+            });
+        }
+        while let Some(res) = tasks.join_next().await {
+            match res? {
+                (vtuber, Ok(videos)) => {
+                    schedule.extend(videos.into_iter().map(|video| (vtuber.clone(), video)))
+                }
+                (vtuber, Err(e)) => {
+                    warn!(
+                        "Schedule of {} {} is skipped: {}",
+                        vtuber.first_name, vtuber.last_name, e
+                    );
+                    failed += 1;
+                    last_error = Some(e);
+                }
+            }
+        }
+    }
+    if let (true, Some(e)) = (failed == vtubers.len(), last_error) {
+        return Err(e);
+    }
+    schedule.sort_by_key(|x| x.1.available_at);
+    Ok(schedule)
+}
+
+// Fetch upcoming videos of one vtuber within the horizon. Weekly horizon can have more
+// videos, than one page holds
+async fn fetch_channel_schedule(
+    holodex_api: Arc<holodex::Client>,
+    vtuber: &Vtuber,
+    horizon_hours: u32,
+) -> anyhow::Result<Vec<Video>> {
+    let channel_id: ChannelId = vtuber.youtube_channel_id.parse()?;
+    holodex_request::request_all_videos(holodex_api, move |offset| {
+        VideoFilterBuilder::new()
+            .channel_id(channel_id.clone())
+            .status(&[VideoStatus::Upcoming])
+            .max_upcoming_hours(horizon_hours)
+            .sort_by(VideoSortingCriteria::StartScheduled)
+            .limit(holodex_request::PAGE_SIZE)
+            .offset(offset)
+            .build()
+    })
+    .await
+}
+
+// Length of schedule message. Telegram allows 4096 characters, markup is counted too to stay
+// on the safe side
+const MESSAGE_MAX_LENGTH: usize = 4000;
+
+// Render schedule, grouped by days in user timezone, as MarkdownV2 messages. Title goes
+// before the first day, long schedule is split into several messages
+pub fn render_schedule(
+    schedule: &[(Vtuber, Video)],
+    user_timezone: &str,
+    title: Option<&str>,
+) -> Vec<String> {
+    if schedule.is_empty() {
+        return vec![String::from("Ближайших стримов у выбранных втуберов нет")];
+    }
+    let today = timezone::to_user_time(chrono::Utc::now(), user_timezone).date_naive();
+    let mut days: Vec<(String, Vec<String>)> = Vec::new();
+    let mut current_day = None;
+    for (vtuber, video) in schedule {
+        let local = timezone::to_user_time(video.available_at, user_timezone);
+        let day = local.date_naive();
+        // Header for every new day
+        if current_day != Some(day) {
+            let day_name = match (day - today).num_days() {
+                0 => "Сегодня",
+                1 => "Завтра",
+                _ => "",
+            };
+            days.push((
+                format!(
+                    "*{}*\n",
+                    mobot::api::escape_md(
+                        format!("{} {:02}.{:02}", day_name, day.day(), day.month()).trim()
+                    )
+                ),
+                Vec::new(),
+            ));
+            current_day = Some(day);
+        }
+        if let Some((_, entries)) = days.last_mut() {
+            entries.push(format!(
+                "• {} {} \\- [{}](https://www.youtube.com/watch?v={})\n",
+                local.format("%H:%M"),
+                mobot::api::escape_md(&format!("{} {}", vtuber.first_name, vtuber.last_name)),
+                mobot::api::escape_md(&video.title),
+                video.id.to_string()
+            ));
+        }
+    }
+    let title = title.map_or(String::new(), |x| format!("{}\n", mobot::api::escape_md(x)));
+    let footer = format!(
+        "\nВремя указано в часовом поясе {}",
+        mobot::api::escape_md(user_timezone)
+    );
+    pack_messages(&title, days, &footer, MESSAGE_MAX_LENGTH)
+}
+
+// Pack days of the schedule into messages, that fit into the length. Message is split only
+// between entries, so escapes and links stay whole. Day, that continues in the next message,
+// repeats its header there. Footer goes after the last day
+fn pack_messages(
+    title: &str,
+    days: Vec<(String, Vec<String>)>,
+    footer: &str,
+    max_length: usize,
+) -> Vec<String> {
+    // Telegram counts length in UTF-16 code units
+    let length = |x: &str| x.encode_utf16().count();
+    let mut messages = Vec::new();
+    let mut text = String::from(title);
+    for (header, entries) in days {
+        let mut has_header = false;
+        for entry in entries {
+            let prefix = match (has_header, text.is_empty()) {
+                (true, _) => String::new(),
+                (false, true) => header.clone(),
+                (false, false) => format!("\n{}", header),
+            };
+            let new_length = length(&text) + length(&prefix) + length(&entry) + length(footer);
+            match new_length > max_length && !text.is_empty() {
+                true => {
+                    messages.push(text.trim_end().to_string());
+                    text = header.clone();
+                }
+                false => text.push_str(&prefix),
+            }
+            text.push_str(&entry);
+            has_header = true;
+        }
+    }
+    text.push_str(footer);
+    messages.push(text);
+    messages
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn day(header: &str, entries: usize) -> (String, Vec<String>) {
+        (
+            format!("*{}*\n", header),
+            (0..entries)
+                .map(|i| format!("• {} \\- [entry {}](https://youtu.be/{})\n", header, i, i))
+                .collect(),
+        )
+    }
+
+    #[test]
+    fn short_schedule_is_one_message() {
+        let messages = pack_messages("Title\n", vec![day("A", 2), day("B", 1)], "\nFooter", 4000);
+        assert_eq!(messages.len(), 1);
+        assert!(messages[0].starts_with("Title\n\n*A*\n• A"));
+        assert!(messages[0].contains("\n\n*B*\n"));
+        assert!(messages[0].ends_with("\nFooter"));
+    }
+
+    #[test]
+    fn long_schedule_is_split_between_entries() {
+        let days = vec![day("A", 30), day("B", 30), day("C", 30)];
+        let entries = days.iter().flat_map(|x| x.1.clone()).collect::<Vec<_>>();
+        let messages = pack_messages("", days, "\nFooter", 500);
+        assert!(messages.len() > 1);
+        for message in &messages {
+            assert!(message.encode_utf16().count() <= 500);
+            // Every message starts with a day header, entries and links are whole
+            assert!(message.starts_with('*'));
+            for line in message.lines().filter(|x| x.starts_with('•')) {
+                assert!(entries.contains(&format!("{}\n", line)));
+            }
+        }
+        // Nothing is lost or repeated
+        let sent = messages
+            .iter()
+            .flat_map(|x| x.lines())
+            .filter(|x| x.starts_with('•'))
+            .count();
+        assert_eq!(sent, entries.len());
+        assert!(messages.last().unwrap().ends_with("\nFooter"));
+        assert!(!messages[0].contains("Footer"));
+    }
+}
@@ -0,0 +1,333 @@
+use log::warn;
+use mobot::api::{
+    EditMessageReplyMarkupRequest, EditMessageTextRequest, SendMessageRequest, SendPhotoRequest,
+};
+use std::collections::HashMap;
+use std::sync::Arc;
+use tokio::sync::Mutex;
+use tokio::time::{sleep, Duration, Instant};
+
+// Telegram limits: ~30 messages per second overall and 1 message per second in one chat
+const GLOBAL_RATE_PER_SEC: f64 = 30.0;
+const CHAT_RATE_PER_SEC: f64 = 1.0;
+
+// Attempts for transient failures and first backoff delay (doubled after every attempt)
+const MAX_ATTEMPTS: u32 = 5;
+const BASE_BACKOFF_MS: u64 = 500;
+
+// Message, that is sent through the queue. Edits of messages in the chat (menus) count
+// towards the limits too
+#[derive(Clone, Debug)]
+pub enum OutboundMessage {
+    Text(SendMessageRequest),
+    Photo(SendPhotoRequest),
+    // Chat id and request
+    EditText(i64, EditMessageTextRequest),
+    EditMarkup(i64, EditMessageReplyMarkupRequest),
+}
+
+// Token bucket. Tokens can go below zero - that's reservation of the next free slot,
+// so concurrent senders queue up instead of racing for the same token
+#[derive(Debug)]
+struct TokenBucket {
+    tokens: f64,
+    capacity: f64,
+    rate_per_sec: f64,
+    last_refill: Instant,
+}
+
+impl TokenBucket {
+    fn new(capacity: f64, rate_per_sec: f64) -> Self {
+        TokenBucket {
+            tokens: capacity,
+            capacity,
+            rate_per_sec,
+            last_refill: Instant::now(),
+        }
+    }
+    // Take one token at 'now' (can be in the future) and get time to wait before using it.
+    // Refill only moves forward: otherwise time between an earlier 'now' and a reservation
+    // in the future would be credited twice
+    fn reserve(&mut self, now: Instant) -> Duration {
+        if now > self.last_refill {
+            let elapsed = (now - self.last_refill).as_secs_f64();
+            self.tokens = (self.tokens + elapsed * self.rate_per_sec).min(self.capacity);
+            self.last_refill = now;
+        }
+        self.tokens -= 1.0;
+        // Tokens are counted at 'last_refill', which can be later than 'now'
+        let ready_at = match self.tokens < 0.0 {
+            true => self.last_refill + Duration::from_secs_f64(-self.tokens / self.rate_per_sec),
+            false => self.last_refill,
+        };
+        ready_at.saturating_duration_since(now)
+    }
+    // Give no tokens till 'until': telegram asked to wait. Bucket starts from one token then,
+    // so senders don't burst right after the pause
+    fn pause(&mut self, until: Instant) {
+        if until > self.last_refill {
+            self.tokens = self.tokens.min(1.0);
+            self.last_refill = until;
+        }
+    }
+    // Bucket is full again, so it can be forgotten
+    fn is_idle(&self, now: Instant) -> bool {
+        self.tokens + now.duration_since(self.last_refill).as_secs_f64() * self.rate_per_sec
+            >= self.capacity
+    }
+}
+
+#[derive(Debug)]
+struct Limits {
+    global: TokenBucket,
+    chats: HashMap<i64, TokenBucket>,
+}
+
+// Outbound queue for telegram messages. Every message waits for its slot in global and
+// per-chat token buckets, 429 responses pause the global bucket and are retried after
+// 'retry_after', transient failures are retried with exponential backoff
+#[derive(Clone)]
+pub struct SendQueue {
+    tg_api: Arc<mobot::API>,
+    limits: Arc<Mutex<Limits>>,
+}
+
+impl SendQueue {
+    pub fn new(tg_api: Arc<mobot::API>) -> Self {
+        SendQueue {
+            tg_api,
+            limits: Arc::new(Mutex::new(Limits {
+                global: TokenBucket::new(GLOBAL_RATE_PER_SEC, GLOBAL_RATE_PER_SEC),
+                chats: HashMap::new(),
+            })),
+        }
+    }
+    // Wait for free slot to send message into the chat
+    async fn acquire(&self, chat_id: i64) {
+        let delay = {
+            let mut limits = self.limits.lock().await;
+            let now = Instant::now();
+            limits.chats.retain(|_, bucket| !bucket.is_idle(now));
+            let chat_delay = limits
+                .chats
+                .entry(chat_id)
+                .or_insert_with(|| TokenBucket::new(1.0, CHAT_RATE_PER_SEC))
+                .reserve(now);
+            // Global slot is taken after the chat one, so a busy chat doesn't hold it
+            let global_delay = limits.global.reserve(now + chat_delay);
+            chat_delay + global_delay
+        };
+        if !delay.is_zero() {
+            sleep(delay).await;
+        }
+    }
+    // Send message, respecting rate limits and retrying failures
+    pub async fn send(&self, message: OutboundMessage) -> anyhow::Result<()> {
+        let chat_id = match &message {
+            OutboundMessage::Text(req) => req.chat_id,
+            OutboundMessage::Photo(req) => req.chat_id,
+            OutboundMessage::EditText(chat_id, _) => *chat_id,
+            OutboundMessage::EditMarkup(chat_id, _) => *chat_id,
+        };
+        let mut attempt = 1;
+        loop {
+            self.acquire(chat_id).await;
+            let res = match &message {
+                OutboundMessage::Text(req) => self.tg_api.send_message(req).await.map(|_| ()),
+                OutboundMessage::Photo(req) => self.tg_api.send_photo(req).await.map(|_| ()),
+                OutboundMessage::EditText(_, req) => {
+                    self.tg_api.edit_message_text(req).await.map(|_| ())
+                }
+                OutboundMessage::EditMarkup(_, req) => {
+                    self.tg_api.edit_message_reply_markup(req).await.map(|_| ())
+                }
+            };
+            let e = match res {
+                Ok(_) => return Ok(()),
+                Err(e) => e,
+            };
+            let delay = match parse_retry_after(&e.to_string()) {
+                // Limit is shared by the whole bot, so everyone waits
+                Some(retry_after) => {
+                    let delay = Duration::from_secs(retry_after);
+                    self.limits
+                        .lock()
+                        .await
+                        .global
+                        .pause(Instant::now() + delay);
+                    delay
+                }
+                None if is_transient(&e.to_string()) => {
+                    Duration::from_millis(BASE_BACKOFF_MS * 2u64.pow(attempt - 1))
+                }
+                None => return Err(e),
+            };
+            if attempt >= MAX_ATTEMPTS {
+                return Err(e);
+            }
+            warn!(
+                "Sending to chat {} failed (attempt {}): {}. Retry in {:?}",
+                chat_id, attempt, e, delay
+            );
+            sleep(delay).await;
+            attempt += 1;
+        }
+    }
+}
+
+// Get 'retry_after' from telegram 429 error, e.g. 'Too Many Requests: retry after 35'
+fn parse_retry_after(error: &str) -> Option<u64> {
+    let (_, rest) = error.split_once("retry after ")?;
+    rest.chars()
+        .take_while(|c| c.is_ascii_digit())
+        .collect::<String>()
+        .parse()
+        .ok()
+}
+
+// Network problems and telegram server errors are worth retrying
+fn is_transient(error: &str) -> bool {
+    let error = error.to_lowercase();
+    [
+        "timed out",
+        "timeout",
+        "connection",
+        "internal server error",
+        "bad gateway",
+        "service unavailable",
+        "gateway timeout",
+    ]
+    .iter()
+    .any(|x| error.contains(x))
+}
+
+// Failure can go away by itself: telegram asked to slow down, network or server problems.
+// Other failures (bad request, chat not found, etc.) repeat on every attempt
+pub fn is_temporary(error: &str) -> bool {
+    parse_retry_after(error).is_some() || is_transient(error)
+}
+
+// Telegram refuses to deliver anything to the chat: user blocked the bot or deleted account
+pub fn is_blocked(error: &str) -> bool {
+    let error = error.to_lowercase();
+    ["bot was blocked by the user", "user is deactivated"]
+        .iter()
+        .any(|x| error.contains(x))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bucket_spends_capacity_then_spaces_tokens() {
+        let mut bucket = TokenBucket::new(2.0, 2.0);
+        let now = Instant::now();
+        assert_eq!(bucket.reserve(now), Duration::ZERO);
+        assert_eq!(bucket.reserve(now), Duration::ZERO);
+        assert_eq!(bucket.reserve(now), Duration::from_millis(500));
+        assert_eq!(bucket.reserve(now), Duration::from_millis(1000));
+    }
+
+    #[test]
+    fn bucket_refills_with_time() {
+        let mut bucket = TokenBucket::new(1.0, 1.0);
+        let now = Instant::now();
+        assert_eq!(bucket.reserve(now), Duration::ZERO);
+        assert_eq!(bucket.reserve(now + Duration::from_secs(1)), Duration::ZERO);
+        // Long pause doesn't give more than capacity
+        let later = now + Duration::from_secs(60);
+        assert_eq!(bucket.reserve(later), Duration::ZERO);
+        assert_eq!(bucket.reserve(later), Duration::from_secs(1));
+    }
+
+    #[test]
+    fn future_reservation_is_not_credited_twice() {
+        let mut bucket = TokenBucket::new(1.0, 1.0);
+        let now = Instant::now();
+        // Slot for a chat, that is free only in 2 seconds
+        assert_eq!(bucket.reserve(now + Duration::from_secs(2)), Duration::ZERO);
+        // Next sender can't use the same 2 seconds again
+        assert_eq!(bucket.reserve(now), Duration::from_secs(3));
+        assert!(!bucket.is_idle(now));
+    }
+
+    #[test]
+    fn paused_bucket_gives_tokens_after_pause() {
+        let mut bucket = TokenBucket::new(GLOBAL_RATE_PER_SEC, GLOBAL_RATE_PER_SEC);
+        let now = Instant::now();
+        assert_eq!(bucket.reserve(now), Duration::ZERO);
+        bucket.pause(now + Duration::from_secs(5));
+        // Nobody gets a token during the pause, then tokens come one by one
+        assert_eq!(bucket.reserve(now), Duration::from_secs(5));
+        assert_eq!(
+            bucket.reserve(now),
+            Duration::from_secs(5) + Duration::from_secs_f64(1.0 / GLOBAL_RATE_PER_SEC)
+        );
+        // Earlier pause doesn't shorten the current one
+        bucket.pause(now + Duration::from_secs(1));
+        assert!(bucket.reserve(now) > Duration::from_secs(5));
+    }
+
+    #[test]
+    fn bucket_keeps_rate_under_mixed_reservations() {
+        let mut bucket = TokenBucket::new(GLOBAL_RATE_PER_SEC, GLOBAL_RATE_PER_SEC);
+        let now = Instant::now();
+        let mut sends = (0..200)
+            .map(|i| {
+                // Every third sender waits for its chat first
+                let at = match i % 3 {
+                    0 => now + Duration::from_millis(700),
+                    _ => now,
+                };
+                at + bucket.reserve(at)
+            })
+            .collect::<Vec<_>>();
+        sends.sort();
+        // Any second holds at most capacity + rate messages (burst and refill)
+        for (i, start) in sends.iter().enumerate() {
+            let in_window = sends[i..]
+                .iter()
+                .take_while(|x| **x < *start + Duration::from_secs(1))
+                .count();
+            assert!(in_window as f64 <= 2.0 * GLOBAL_RATE_PER_SEC);
+        }
+        let last = *sends.last().unwrap();
+        assert!(last - now >= Duration::from_secs_f64(170.0 / GLOBAL_RATE_PER_SEC));
+    }
+
+    #[test]
+    fn retry_after_is_parsed_from_error() {
+        assert_eq!(
+            parse_retry_after("Too Many Requests: retry after 35"),
+            Some(35)
+        );
+        assert_eq!(
+            parse_retry_after("Telegram error 429: Too Many Requests: retry after 7 (flood)"),
+            Some(7)
+        );
+        assert_eq!(
+            parse_retry_after("Forbidden: bot was blocked by the user"),
+            None
+        );
+        assert_eq!(parse_retry_after("retry after soon"), None);
+    }
+
+    #[test]
+    fn blocked_and_transient_errors_are_recognized() {
+        assert!(is_blocked("Forbidden: bot was blocked by the user"));
+        assert!(is_blocked("Forbidden: user is deactivated"));
+        assert!(!is_blocked("Bad Request: chat not found"));
+        assert!(is_transient("Gateway Timeout"));
+        assert!(!is_transient("Bad Request: can't parse entities"));
+    }
+
+    #[test]
+    fn temporary_errors_are_recognized() {
+        assert!(is_temporary("Too Many Requests: retry after 35"));
+        assert!(is_temporary("Bad Gateway"));
+        assert!(!is_temporary("Bad Request: message is too long"));
+        assert!(!is_temporary("Bad Request: chat not found"));
+        assert!(!is_temporary("Forbidden: bot was blocked by the user"));
+    }
+}
@@ -0,0 +1,59 @@
+use chrono::{DateTime, Utc};
+use chrono_tz::Tz;
+
+// Timezone for users, that didn't choose one
+pub const DEFAULT_TIMEZONE: &str = "Europe/Moscow";
+
+// Timezones, offered in settings menu. Any other IANA timezone can be set with /timezone
+pub const TIMEZONE_OPTIONS: [&str; 10] = [
+    "Europe/London",
+    "Europe/Berlin",
+    "Europe/Kyiv",
+    "Europe/Moscow",
+    "Asia/Yekaterinburg",
+    "Asia/Novosibirsk",
+    "Asia/Tokyo",
+    "America/New_York",
+    "America/Los_Angeles",
+    "Australia/Sydney",
+];
+
+// Parse IANA timezone name
+pub fn parse_timezone(name: &str) -> Option<Tz> {
+    name.parse::<Tz>().ok()
+}
+
+// Convert UTC datetime into user timezone. Falls back to default timezone for unknown names
+pub fn to_user_time(datetime: DateTime<Utc>, timezone: &str) -> DateTime<Tz> {
+    let tz = parse_timezone(timezone).unwrap_or_else(|| parse_timezone(DEFAULT_TIMEZONE).unwrap());
+    datetime.with_timezone(&tz)
+}
+
+// Format time in user timezone, e.g. '21:00 (CEST Europe/Berlin)'. Escaped for MarkdownV2
+pub fn format_user_time(datetime: DateTime<Utc>, timezone: &str) -> String {
+    format_local_time(to_user_time(datetime, timezone), "%H:%M")
+}
+
+// Format new time of the moved event in user timezone. Date is added, e.g.
+// '06.10 21:00 (CEST Europe/Berlin)', if the event moved to another local day
+pub fn format_moved_time(
+    datetime: DateTime<Utc>,
+    previous: DateTime<Utc>,
+    timezone: &str,
+) -> String {
+    let local = to_user_time(datetime, timezone);
+    match local.date_naive() == to_user_time(previous, timezone).date_naive() {
+        true => format_local_time(local, "%H:%M"),
+        false => format_local_time(local, "%d.%m %H:%M"),
+    }
+}
+
+// Format local time with timezone name. Escaped for MarkdownV2
+fn format_local_time(local: DateTime<Tz>, format: &str) -> String {
+    mobot::api::escape_md(&format!(
+        "{} ({} {})",
+        local.format(format),
+        local.format("%Z"),
+        local.timezone().name()
+    ))
+}
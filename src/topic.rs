@@ -0,0 +1,129 @@
+use std::fmt;
+
+// Holodex topics, that user can filter by
+pub const TOPIC_OPTIONS: [&str; 9] = [
+    "membersonly",
+    "FreeChat",
+    "Chatting",
+    "singing",
+    "asmr",
+    "Game",
+    "3D_Stream",
+    "Music_Cover",
+    "Original_Song",
+];
+
+// How user topics are applied
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TopicMode {
+    // Skip videos with selected topics
+    Blacklist,
+    // Notify only about videos with selected topics
+    Whitelist,
+}
+
+impl TopicMode {
+    // Name of the mode for messages
+    pub fn label(&self) -> &'static str {
+        match self {
+            TopicMode::Blacklist => "Не напоминать о выбранных темах",
+            TopicMode::Whitelist => "Напоминать только о выбранных темах",
+        }
+    }
+    // Mode, that button switches to
+    pub fn toggled(&self) -> TopicMode {
+        match self {
+            TopicMode::Blacklist => TopicMode::Whitelist,
+            TopicMode::Whitelist => TopicMode::Blacklist,
+        }
+    }
+}
+
+impl fmt::Display for TopicMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TopicMode::Blacklist => write!(f, "blacklist"),
+            TopicMode::Whitelist => write!(f, "whitelist"),
+        }
+    }
+}
+
+impl std::str::FromStr for TopicMode {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "blacklist" => Ok(TopicMode::Blacklist),
+            "whitelist" => Ok(TopicMode::Whitelist),
+            _ => Err(anyhow::anyhow!("Unknown topic mode: {}", s)),
+        }
+    }
+}
+
+// Check video topic against user rules. Holodex isn't consistent with case of topic ids
+pub fn is_allowed(topic: Option<&str>, mode: TopicMode, topics: &[String]) -> bool {
+    let is_selected = match topic {
+        Some(topic) => topics.iter().any(|x| x.eq_ignore_ascii_case(topic)),
+        None => false,
+    };
+    match mode {
+        TopicMode::Blacklist => !is_selected,
+        TopicMode::Whitelist => is_selected,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn topics(names: &[&str]) -> Vec<String> {
+        names.iter().map(|x| String::from(*x)).collect()
+    }
+
+    #[test]
+    fn blacklist_skips_selected_topics() {
+        let selected = topics(&["asmr"]);
+        assert!(!is_allowed(Some("asmr"), TopicMode::Blacklist, &selected));
+        assert!(is_allowed(Some("singing"), TopicMode::Blacklist, &selected));
+    }
+
+    #[test]
+    fn whitelist_keeps_only_selected_topics() {
+        let selected = topics(&["singing", "Game"]);
+        assert!(is_allowed(Some("Game"), TopicMode::Whitelist, &selected));
+        assert!(!is_allowed(Some("asmr"), TopicMode::Whitelist, &selected));
+    }
+
+    #[test]
+    fn topic_case_is_ignored() {
+        let selected = topics(&["FreeChat"]);
+        assert!(is_allowed(
+            Some("freechat"),
+            TopicMode::Whitelist,
+            &selected
+        ));
+        assert!(!is_allowed(
+            Some("FREECHAT"),
+            TopicMode::Blacklist,
+            &selected
+        ));
+    }
+
+    #[test]
+    fn video_without_topic() {
+        // Blacklist has nothing to match, whitelist has nothing to allow
+        assert!(is_allowed(None, TopicMode::Blacklist, &topics(&["asmr"])));
+        assert!(!is_allowed(None, TopicMode::Whitelist, &topics(&["asmr"])));
+        // Empty blacklist allows everything
+        assert!(is_allowed(Some("asmr"), TopicMode::Blacklist, &[]));
+    }
+
+    #[test]
+    fn mode_round_trips_through_string() {
+        for mode in [TopicMode::Blacklist, TopicMode::Whitelist] {
+            assert_eq!(mode.to_string().parse::<TopicMode>().unwrap(), mode);
+            assert_eq!(mode.toggled().toggled(), mode);
+        }
+        assert!("greylist".parse::<TopicMode>().is_err());
+    }
+}
@@ -1,6 +1,8 @@
 use serde;
 use serde::Deserialize;
 
+use crate::topic::TopicMode;
+
 // User struct
 #[derive(Deserialize, Clone, Debug, Default)]
 pub struct User {
@@ -10,4 +12,25 @@ pub struct User {
     pub username: Option<String>,
     pub tg_user_id: i64,
     pub tg_chat_id: i64,
+    // IANA timezone name
+    pub timezone: String,
+    // Remind at the start of the stream
+    pub started_ping: bool,
+    // How selected topics are applied: blacklist or whitelist
+    pub topic_mode: String,
+    // Group reminders of one poll cycle into single message
+    pub digest_mode: bool,
+    // Local time of daily schedule digest ('HH:MM'), None if disabled
+    pub digest_time: Option<String>,
+    // Send weekly overview on Sundays
+    pub weekly_digest: bool,
+    // False, if user blocked the bot or deleted the account
+    pub is_active: bool,
+}
+
+impl User {
+    // Get parsed topic mode
+    pub fn get_topic_mode(&self) -> TopicMode {
+        self.topic_mode.parse().unwrap_or(TopicMode::Blacklist)
+    }
 }
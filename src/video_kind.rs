@@ -0,0 +1,65 @@
+use holodex::model::{Video, VideoType};
+use std::fmt;
+
+// Kind of video, that users can be notified about
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum VideoKind {
+    // Live stream
+    Stream,
+    // Premiere of uploaded video (music video, 3D showcase, etc.)
+    Premiere,
+    // Holodex placeholder for external event
+    Placeholder,
+}
+
+// Kinds of videos in order, they are shown in settings. Holodex clips are fan-made videos on
+// clippers' channels without start time, so there is nothing to remind about
+pub const VIDEO_KIND_OPTIONS: [VideoKind; 3] = [
+    VideoKind::Stream,
+    VideoKind::Premiere,
+    VideoKind::Placeholder,
+];
+
+impl VideoKind {
+    // Detect kind of holodex video. Holodex has no separate type for premieres, but premiere
+    // has duration of the uploaded video before and during airing, while stream gets it only
+    // after the end. Ended videos aren't fetched, so kind doesn't change, when video goes live
+    pub fn of(video: &Video) -> VideoKind {
+        match video.video_type {
+            VideoType::Placeholder => VideoKind::Placeholder,
+            _ if video.duration.num_seconds() > 0 => VideoKind::Premiere,
+            _ => VideoKind::Stream,
+        }
+    }
+    // Name of the kind for messages
+    pub fn label(&self) -> &'static str {
+        match self {
+            VideoKind::Stream => "Стрим",
+            VideoKind::Premiere => "Премьера",
+            VideoKind::Placeholder => "Событие",
+        }
+    }
+}
+
+impl fmt::Display for VideoKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            VideoKind::Stream => write!(f, "stream"),
+            VideoKind::Premiere => write!(f, "premiere"),
+            VideoKind::Placeholder => write!(f, "placeholder"),
+        }
+    }
+}
+
+impl std::str::FromStr for VideoKind {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "stream" => Ok(VideoKind::Stream),
+            "premiere" => Ok(VideoKind::Premiere),
+            "placeholder" => Ok(VideoKind::Placeholder),
+            _ => Err(anyhow::anyhow!("Unknown video kind: {}", s)),
+        }
+    }
+}
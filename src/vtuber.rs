@@ -1,6 +1,9 @@
+use chrono;
 use serde;
 use serde::Deserialize;
 
+use crate::vtuber_status::VtuberStatus;
+
 // Vtuber struct
 #[derive(Deserialize, Clone, Debug, Default)]
 pub struct Vtuber {
@@ -13,11 +16,61 @@ pub struct Vtuber {
     pub emoji: String,
     pub youtube_channel_id: String,
     pub youtube_handle: String,
+    #[serde(skip)]
+    pub organization: String,
+    #[serde(skip)]
+    pub branch: String,
+    // False for channels, added by holodex sync and not checked by admin yet
+    #[serde(skip)]
+    pub is_confirmed: bool,
+    // Channel name on holodex, None until first sync
+    #[serde(skip)]
+    pub holodex_name: Option<String>,
+    // Lifecycle status: active, graduated or hiatus. Empty, if data.json doesn't set it
+    #[serde(default)]
+    pub status: String,
 }
 
-// Represents vtuber wave
+impl Vtuber {
+    // Get parsed status
+    pub fn get_status(&self) -> VtuberStatus {
+        self.status.parse().unwrap_or(VtuberStatus::Active)
+    }
+}
+
+// Represents vtuber wave (or other group inside the branch)
 #[derive(Deserialize, Clone, Debug, Default)]
 pub struct VtuberWave {
     pub name: String,
+    #[serde(default)]
+    pub debut_date: Option<chrono::NaiveDate>,
+    #[serde(default)]
+    pub description: Option<String>,
     pub members: Vec<Vtuber>,
 }
+
+// Wave, as it is stored in db. Waves of the branch are shown in display order
+#[derive(Clone, Debug, Default)]
+pub struct Wave {
+    pub id: i64,
+    pub organization: String,
+    pub branch: String,
+    pub name: String,
+    pub display_order: i64,
+    pub debut_date: Option<chrono::NaiveDate>,
+    pub description: Option<String>,
+}
+
+// Represents branch of organization, e.g. Nijisanji EN
+#[derive(Deserialize, Clone, Debug, Default)]
+pub struct VtuberBranch {
+    pub name: String,
+    pub waves: Vec<VtuberWave>,
+}
+
+// Represents organization (agency). Indies can be grouped into their own 'organization'
+#[derive(Deserialize, Clone, Debug, Default)]
+pub struct VtuberOrganization {
+    pub name: String,
+    pub branches: Vec<VtuberBranch>,
+}
@@ -0,0 +1,57 @@
+use std::fmt;
+
+// Lifecycle status of vtuber in the catalog
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum VtuberStatus {
+    Active,
+    // Vtuber retired, streams are over
+    Graduated,
+    // Vtuber takes a break
+    Hiatus,
+}
+
+impl VtuberStatus {
+    // Badge in members markup
+    pub fn badge(&self) -> &'static str {
+        match self {
+            VtuberStatus::Active => "",
+            VtuberStatus::Graduated => "🎓",
+            VtuberStatus::Hiatus => "💤",
+        }
+    }
+    // Notice for subscribers, when vtuber gets the status
+    pub fn notice(&self) -> Option<&'static str> {
+        match self {
+            VtuberStatus::Active => None,
+            VtuberStatus::Graduated => Some(
+                "завершает деятельность (graduation). Стримов больше не будет, втубер скрыт из каталога",
+            ),
+            VtuberStatus::Hiatus => Some(
+                "уходит на перерыв (hiatus). Напоминания придут, когда стримы возобновятся",
+            ),
+        }
+    }
+}
+
+impl fmt::Display for VtuberStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            VtuberStatus::Active => write!(f, "active"),
+            VtuberStatus::Graduated => write!(f, "graduated"),
+            VtuberStatus::Hiatus => write!(f, "hiatus"),
+        }
+    }
+}
+
+impl std::str::FromStr for VtuberStatus {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "active" => Ok(VtuberStatus::Active),
+            "graduated" => Ok(VtuberStatus::Graduated),
+            "hiatus" => Ok(VtuberStatus::Hiatus),
+            _ => Err(anyhow::anyhow!("Unknown vtuber status: {}", s)),
+        }
+    }
+}